@@ -0,0 +1,178 @@
+use crate::{
+    priority::{AsciiLetters, PriorityScheme},
+    Item, Priority,
+};
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{BitAnd, BitOr},
+};
+
+// The set operations compartments and badges need, so the ASCII letters can
+// keep their bitmask while other schemes use a `CharSet`.
+pub(crate) trait Items:
+    Clone
+    + Default
+    + Eq
+    + fmt::Debug
+    + FromIterator<Item>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+{
+    fn insert(&mut self, item: Item);
+
+    fn contains(&self, item: &Item) -> bool;
+
+    fn len(&self) -> usize;
+
+    fn iter(&self) -> impl Iterator<Item = Item> + '_;
+
+    fn priority_with<S: PriorityScheme + ?Sized>(&self, scheme: &S) -> Priority {
+        self.iter()
+            .map(|item| scheme.priority(item).expect("item outside priority scheme"))
+            .sum()
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct ItemSet(u64);
+
+impl ItemSet {
+    pub(crate) fn priority(&self) -> Priority {
+        self.priority_with(&AsciiLetters)
+    }
+
+    fn bit(item: Item) -> u64 {
+        let priority = AsciiLetters
+            .priority(item)
+            .expect("ItemSet only holds ASCII letters");
+        1 << priority.0
+    }
+}
+
+impl Items for ItemSet {
+    fn insert(&mut self, item: Item) {
+        self.0 |= Self::bit(item);
+    }
+
+    fn contains(&self, item: &Item) -> bool {
+        self.0 & Self::bit(*item) != 0
+    }
+
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Item> + '_ {
+        Iter(self.0)
+    }
+}
+
+impl BitAnd for ItemSet {
+    type Output = ItemSet;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        ItemSet(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for ItemSet {
+    type Output = ItemSet;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        ItemSet(self.0 | rhs.0)
+    }
+}
+
+impl FromIterator<Item> for ItemSet {
+    fn from_iter<I: IntoIterator<Item = Item>>(iter: I) -> Self {
+        iter.into_iter().fold(ItemSet::default(), |mut set, item| {
+            set.insert(item);
+            set
+        })
+    }
+}
+
+impl IntoIterator for ItemSet {
+    type Item = Item;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter(self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Iter(u64);
+
+impl Iterator for Iter {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let priority = self.0.trailing_zeros() as i32;
+        self.0 &= self.0 - 1;
+        Some(Item::from_priority(Priority(priority)))
+    }
+}
+
+impl Sum<ItemSet> for Priority {
+    fn sum<I: Iterator<Item = ItemSet>>(iter: I) -> Self {
+        iter.map(|set| set.priority()).sum()
+    }
+}
+
+// Any characters, kept sorted, for the table and Unicode schemes.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct CharSet(Vec<char>);
+
+impl Items for CharSet {
+    fn insert(&mut self, item: Item) {
+        if let Err(index) = self.0.binary_search(&item.0) {
+            self.0.insert(index, item.0);
+        }
+    }
+
+    fn contains(&self, item: &Item) -> bool {
+        self.0.binary_search(&item.0).is_ok()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Item> + '_ {
+        self.0.iter().copied().map(Item)
+    }
+}
+
+impl BitAnd for CharSet {
+    type Output = CharSet;
+
+    fn bitand(mut self, rhs: Self) -> Self::Output {
+        self.0.retain(|item| rhs.0.binary_search(item).is_ok());
+        self
+    }
+}
+
+impl BitOr for CharSet {
+    type Output = CharSet;
+
+    fn bitor(mut self, rhs: Self) -> Self::Output {
+        self.0.extend(rhs.0);
+        self.0.sort_unstable();
+        self.0.dedup();
+        self
+    }
+}
+
+impl FromIterator<Item> for CharSet {
+    fn from_iter<I: IntoIterator<Item = Item>>(iter: I) -> Self {
+        iter.into_iter().fold(CharSet::default(), |mut set, item| {
+            set.insert(item);
+            set
+        })
+    }
+}
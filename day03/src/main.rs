@@ -1,37 +1,225 @@
-use itertools::Itertools;
-use std::{collections::HashSet, iter::Sum};
+use item_set::{CharSet, ItemSet, Items};
+use parsing::{read_rucksacks_in_groups, Defect, DefectKind, ParseError};
+use priority::{AsciiLetters, PriorityScheme, Table, UnicodeLetters};
+use report::{write_report, Format};
+use solver::partition_into_groups;
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Write},
+    iter::Sum,
+    process,
+};
+use stream::{summarise, Unresolved};
+
+mod item_set;
+mod parsing;
+mod priority;
+mod rebalance;
+mod report;
+mod solver;
+mod stream;
+
+const DEFAULT_GROUP_SIZE: usize = 3;
 
 fn main() {
-    let result: Priority = rucksacks()
-        .fold(Vec::new(), |mut items, rucksack| {
-            items.extend(rucksack.common_items_in_both_compartments().into_iter());
-            items
-        })
-        .into_iter()
-        .map(Item::priority)
+    let custom_scheme = priority_scheme();
+    let input = input();
+
+    match custom_scheme {
+        Some(scheme) => run(input, scheme.as_ref()),
+        None => run(input, &AsciiLetters),
+    }
+}
+
+fn run<S: PriorityScheme + ?Sized>(input: impl BufRead, scheme: &S) {
+    if flag("--shuffled") || flag("--repair") || option("--report").is_some() {
+        run_in_memory(input, scheme);
+    } else {
+        run_streaming(input, scheme);
+    }
+}
+
+fn run_streaming<S: PriorityScheme + ?Sized>(input: impl BufRead, scheme: &S) {
+    let summary = summarise(input, DEFAULT_GROUP_SIZE, scheme).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(1);
+    });
+
+    if !summary.diagnostics.is_empty() {
+        eprint!("{}", ParseError(summary.diagnostics));
+        process::exit(1);
+    }
+
+    println!("Part 1: {:?}", summary.part_1);
+
+    for unresolved in &summary.unresolved {
+        eprintln!("{unresolved}");
+    }
+    if !summary.unresolved.is_empty() {
+        process::exit(1);
+    }
+
+    println!("Part 2: {:?}", summary.part_2);
+}
+
+fn run_in_memory<S: PriorityScheme + ?Sized>(input: impl BufRead, scheme: &S) {
+    let rucksacks = match read_rucksacks_in_groups(input, DEFAULT_GROUP_SIZE, scheme) {
+        Ok(Ok(rucksacks)) => rucksacks,
+        Ok(Err(error)) => {
+            eprint!("{error}");
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+    };
+
+    let groups = if flag("--shuffled") {
+        match partition_into_groups::<S::Set>(&rucksacks, DEFAULT_GROUP_SIZE) {
+            Ok(partition) => partition
+                .into_iter()
+                .map(|members| {
+                    let group = Group(members.iter().map(|&i| rucksacks[i].clone()).collect());
+                    (members, group)
+                })
+                .collect(),
+            Err(error) => {
+                eprintln!("{error}");
+                process::exit(1);
+            }
+        }
+    } else {
+        groups(&rucksacks, DEFAULT_GROUP_SIZE)
+            .enumerate()
+            .map(|(index, group)| {
+                let first = index * DEFAULT_GROUP_SIZE;
+                ((first..first + DEFAULT_GROUP_SIZE).collect(), group)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if let Some(format) = option("--report") {
+        let format = format.parse::<Format>().unwrap_or_else(|error| {
+            eprintln!("{error}");
+            process::exit(1);
+        });
+        let mut out = io::stdout().lock();
+        write_report(&mut out, format, &rucksacks, &groups, scheme)
+            .and_then(|_| out.flush())
+            .expect("failed to write report");
+        return;
+    }
+
+    let result: Priority = rucksacks
+        .iter()
+        .map(|rucksack| rucksack.score(scheme))
         .sum();
 
     println!("Part 1: {:?}", result);
 
-    let result: Priority = groups()
-        .map(|group| group.badge())
-        .map(Item::priority)
-        .sum();
+    if flag("--repair") {
+        let plans = rucksacks
+            .iter()
+            .filter_map(|rucksack| rucksack.rebalance(scheme))
+            .collect::<Vec<_>>();
+        println!(
+            "Repair: {} swaps, cost {:?}, {} rucksacks cannot be rebalanced",
+            plans.iter().map(|plan| plan.swaps.len()).sum::<usize>(),
+            plans.iter().map(|plan| plan.cost).sum::<Priority>(),
+            rucksacks.len() - plans.len()
+        );
+    }
+
+    let mut badges = Vec::new();
+    let mut unresolved = false;
+    for (index, (members, group)) in groups.iter().enumerate() {
+        match group.score(scheme) {
+            Ok(priority) => badges.push(priority),
+            Err(badge) => {
+                unresolved = true;
+                eprintln!(
+                    "{}",
+                    Unresolved {
+                        group: index + 1,
+                        members: members.clone(),
+                        badge,
+                    }
+                );
+            }
+        }
+    }
+
+    if unresolved {
+        process::exit(1);
+    }
+
+    let result: Priority = badges.into_iter().sum();
 
     println!("Part 2: {:?}", result);
 }
 
-fn rucksacks() -> impl Iterator<Item = Rucksack> {
-    include_str!("input.txt").lines().map(Rucksack::new)
+fn input() -> Box<dyn BufRead> {
+    match input_path().as_deref() {
+        None => Box::new(include_str!("input.txt").as_bytes()),
+        Some("-") => Box::new(io::stdin().lock()),
+        Some(path) => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                process::exit(1);
+            }
+        },
+    }
 }
 
-fn groups() -> impl Iterator<Item = Group> {
-    rucksacks()
-        .chunks(3)
-        .into_iter()
-        .map(|chunk| Group(chunk.collect::<Vec<_>>().try_into().unwrap()))
-        .collect::<Vec<_>>()
-        .into_iter()
+fn input_path() -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--priorities" | "--report" => {
+                args.next();
+            }
+            _ if arg.starts_with("--") => {}
+            _ => return Some(arg),
+        }
+    }
+    None
+}
+
+fn flag(name: &str) -> bool {
+    env::args().any(|arg| arg == name)
+}
+
+fn option(name: &str) -> Option<String> {
+    env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+fn priority_scheme() -> Option<Box<dyn PriorityScheme<Set = CharSet>>> {
+    if let Some(path) = option("--priorities") {
+        let table = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| text.parse::<Table>().map_err(|error| error.to_string()));
+        match table {
+            Ok(table) => Some(Box::new(table)),
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                process::exit(1);
+            }
+        }
+    } else if flag("--unicode") {
+        Some(Box::new(UnicodeLetters))
+    } else {
+        None
+    }
+}
+
+fn groups(rucksacks: &[Rucksack], size: usize) -> impl Iterator<Item = Group> + '_ {
+    assert!(size > 0);
+    rucksacks
+        .chunks_exact(size)
+        .map(|chunk| Group(chunk.to_vec()))
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -44,20 +232,32 @@ struct Priority(i32);
 struct Rucksack(Vec<Item>);
 
 #[derive(Debug, Clone)]
-struct Group([Rucksack; 3]);
+struct Group(Vec<Rucksack>);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Badge<T = ItemSet> {
+    Unique(Item),
+    Missing {
+        rucksacks: Vec<usize>,
+    },
+    Ambiguous {
+        candidates: T,
+        rucksacks: Vec<usize>,
+    },
+}
 
 impl Item {
+    // Whether an item is valid is up to the priority scheme scoring it.
     fn new(item: char) -> Self {
-        assert!(item.is_ascii_alphabetic());
         Self(item)
     }
 
-    fn priority(self) -> Priority {
-        match self {
-            Item('a'..='z') => Priority(self.0 as i32 - 'a' as i32 + 1),
-            Item('A'..='Z') => Priority(self.0 as i32 - 'A' as i32 + 27),
+    fn from_priority(priority: Priority) -> Self {
+        match priority {
+            Priority(1..=26) => Item((b'a' + (priority.0 - 1) as u8) as char),
+            Priority(27..=52) => Item((b'A' + (priority.0 - 27) as u8) as char),
             _ => {
-                panic!("unexpected item")
+                panic!("unexpected priority")
             }
         }
     }
@@ -70,20 +270,64 @@ impl Sum<Priority> for Priority {
 }
 
 impl Rucksack {
+    #[cfg(test)]
     fn new(items: impl Into<String>) -> Self {
         Self(items.into().chars().map(Item::new).collect())
     }
 
+    #[cfg(test)]
+    fn try_new(items: &str) -> Result<Self, Vec<Defect>> {
+        Self::try_new_with(items, &AsciiLetters)
+    }
+
+    fn try_new_with<S: PriorityScheme + ?Sized>(
+        items: &str,
+        scheme: &S,
+    ) -> Result<Self, Vec<Defect>> {
+        let mut defects = items
+            .chars()
+            .zip(1..)
+            .filter(|(item, _)| !scheme.accepts(*item))
+            .map(|(item, column)| Defect {
+                column,
+                kind: DefectKind::IllegalItem(item),
+            })
+            .collect::<Vec<_>>();
+
+        let len = items.chars().count();
+        if !len.is_multiple_of(2) {
+            defects.push(Defect {
+                column: len,
+                kind: DefectKind::OddLength(len),
+            });
+        }
+
+        if defects.is_empty() {
+            Ok(Self(items.chars().map(Item::new).collect()))
+        } else {
+            Err(defects)
+        }
+    }
+
     fn compartments(&self) -> (&[Item], &[Item]) {
         assert_eq!(self.0.len() % 2, 0);
         self.0.split_at(self.0.len() / 2)
     }
 
-    fn common_items_in_both_compartments(&self) -> HashSet<Item> {
+    fn items<T: Items>(&self) -> T {
+        T::from_iter(self.0.iter().copied())
+    }
+
+    fn common_items_in_both_compartments<T: Items>(&self) -> T {
         let (left, right) = self.compartments();
-        let left = HashSet::<Item>::from_iter(left.iter().copied());
-        let right = HashSet::<Item>::from_iter(right.iter().copied());
-        &left & &right
+        let left = T::from_iter(left.iter().copied());
+        let right = T::from_iter(right.iter().copied());
+        left & right
+    }
+
+    fn score<S: PriorityScheme + ?Sized>(&self, scheme: &S) -> Priority {
+        self.common_items_in_both_compartments::<S::Set>()
+            .priority_with(scheme)
     }
 }
 
@@ -97,36 +341,73 @@ impl IntoIterator for Rucksack {
 }
 
 impl Group {
-    fn badge(&self) -> Item {
-        let mut sets = self.0.iter().cloned().map(HashSet::<Item>::from_iter);
+    fn badge<T: Items>(&self) -> Badge<T> {
+        Badge::of(&self.0.iter().map(Rucksack::items).collect::<Vec<_>>())
+    }
 
-        let intersection = sets
-            .next()
-            .map(|set| sets.fold(set, |set1, set2| &set1 & &set2))
-            .unwrap();
+    // A group without a unique badge has no score; its badge says why.
+    fn score<S: PriorityScheme + ?Sized>(&self, scheme: &S) -> Result<Priority, Badge<S::Set>> {
+        match self.badge() {
+            Badge::Unique(item) => Ok(scheme.priority(item).expect("item outside priority scheme")),
+            badge => Err(badge),
+        }
+    }
+}
 
-        assert_eq!(intersection.len(), 1);
-        intersection.into_iter().next().unwrap()
+impl<T: Items> Badge<T> {
+    fn of(sets: &[T]) -> Self {
+        let intersect = |skip: Option<usize>| {
+            sets.iter()
+                .enumerate()
+                .filter(|(index, _)| Some(*index) != skip)
+                .map(|(_, set)| set.clone())
+                .reduce(|intersection, set| intersection & set)
+        };
+
+        let intersection = intersect(None).unwrap_or_default();
+        match intersection.len() {
+            1 => Badge::Unique(intersection.iter().next().unwrap()),
+            0 => {
+                let rucksacks = (0..sets.len())
+                    .filter(|&index| intersect(Some(index)).is_none_or(|set| set.len() > 0))
+                    .collect::<Vec<_>>();
+                Badge::Missing {
+                    rucksacks: if rucksacks.is_empty() {
+                        (0..sets.len()).collect()
+                    } else {
+                        rucksacks
+                    },
+                }
+            }
+            _ => Badge::Ambiguous {
+                candidates: intersection,
+                rucksacks: (0..sets.len()).collect(),
+            },
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use parsing::{parse_rucksacks, Diagnostic};
 
     #[test]
     fn items_have_priorities() {
-        assert_eq!(Item::new('a').priority(), Priority(1));
-        assert_eq!(Item::new('b').priority(), Priority(2));
-        assert_eq!(Item::new('c').priority(), Priority(3));
+        let priority = |item| AsciiLetters.priority(Item::new(item));
+
+        assert_eq!(priority('a'), Some(Priority(1)));
+        assert_eq!(priority('b'), Some(Priority(2)));
+        assert_eq!(priority('c'), Some(Priority(3)));
 
-        assert_eq!(Item::new('z').priority(), Priority(26));
+        assert_eq!(priority('z'), Some(Priority(26)));
 
-        assert_eq!(Item::new('A').priority(), Priority(27));
-        assert_eq!(Item::new('B').priority(), Priority(28));
-        assert_eq!(Item::new('C').priority(), Priority(29));
+        assert_eq!(priority('A'), Some(Priority(27)));
+        assert_eq!(priority('B'), Some(Priority(28)));
+        assert_eq!(priority('C'), Some(Priority(29)));
 
-        assert_eq!(Item::new('Z').priority(), Priority(52));
+        assert_eq!(priority('Z'), Some(Priority(52)));
+        assert_eq!(priority('é'), None);
     }
 
     #[test]
@@ -143,19 +424,182 @@ mod test {
         assert_eq!(right[0], Item::new('h'));
         assert_eq!(right[11], Item::new('p'));
 
-        let common = rucksack.common_items_in_both_compartments();
+        let common = rucksack.common_items_in_both_compartments::<ItemSet>();
         assert_eq!(common.len(), 1);
         assert!(common.contains(&Item::new('p')));
     }
 
     #[test]
     fn groups_have_a_badge() {
-        let group = Group([
+        let group = Group(vec![
+            Rucksack::new("vJrwpWtwJgWrhcsFMMfFFhFp"),
+            Rucksack::new("jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL"),
+            Rucksack::new("PmmdzqPrVvPwwTWBwg"),
+        ]);
+
+        assert_eq!(group.badge::<ItemSet>(), Badge::Unique(Item::new('r')));
+    }
+
+    #[test]
+    fn groups_can_have_any_size() {
+        let rucksacks = [
+            "vJrwpWtwJgWrhcsFMMfFFhFp",
+            "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+            "PmmdzqPrVvPwwTWBwg",
+            "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+        ]
+        .map(Rucksack::new);
+
+        let badges = groups(&rucksacks, 2)
+            .map(|group| group.badge::<ItemSet>())
+            .collect::<Vec<_>>();
+        assert_eq!(badges.len(), 2);
+        assert!(matches!(badges[1], Badge::Ambiguous { .. }));
+
+        assert_eq!(groups(&rucksacks, 4).count(), 1);
+    }
+
+    #[test]
+    fn groups_without_a_unique_badge_name_the_offending_rucksacks() {
+        let group = Group(vec![
+            Rucksack::new("abcd"),
+            Rucksack::new("bcef"),
+            Rucksack::new("XYZW"),
+        ]);
+        assert_eq!(
+            group.badge::<ItemSet>(),
+            Badge::Missing { rucksacks: vec![2] }
+        );
+
+        let group = Group(vec![Rucksack::new("abcd"), Rucksack::new("bcef")]);
+        assert_eq!(
+            group.badge(),
+            Badge::Ambiguous {
+                candidates: ItemSet::from_iter([Item::new('b'), Item::new('c')]),
+                rucksacks: vec![0, 1],
+            }
+        );
+    }
+
+    #[test]
+    fn item_sets_round_trip_through_priorities() {
+        let all = ItemSet::from_iter(('a'..='z').chain('A'..='Z').map(Item::new));
+        assert_eq!(all.len(), 52);
+        assert_eq!(all.priority_with(&AsciiLetters), Priority((1..=52).sum()));
+
+        for (item, priority) in all.iter().zip(1..) {
+            assert_eq!(AsciiLetters.priority(item), Some(Priority(priority)));
+        }
+    }
+
+    #[test]
+    fn item_sets_have_unions_and_intersections() {
+        let left = ItemSet::from_iter("abcX".chars().map(Item::new));
+        let right = ItemSet::from_iter("cdXY".chars().map(Item::new));
+
+        let both = left & right;
+        assert_eq!(
+            both.iter().collect::<Vec<_>>(),
+            vec![Item::new('c'), Item::new('X')]
+        );
+
+        let either = left | right;
+        assert_eq!(either.len(), 6);
+        assert!(either.contains(&Item::new('Y')));
+        assert!(!either.contains(&Item::new('Z')));
+
+        assert_eq!((ItemSet::default() & left).len(), 0);
+        assert_eq!([left, right].into_iter().sum::<Priority>(), Priority(164));
+    }
+
+    #[test]
+    fn char_sets_hold_any_item() {
+        let left = CharSet::from_iter("abcXé".chars().map(Item::new));
+        let right = CharSet::from_iter("cdXYé1".chars().map(Item::new));
+
+        let both = left.clone() & right.clone();
+        assert_eq!(
+            both.iter().collect::<Vec<_>>(),
+            vec![Item::new('X'), Item::new('c'), Item::new('é')]
+        );
+
+        let either = left | right;
+        assert_eq!(either.len(), 8);
+        assert!(either.contains(&Item::new('1')));
+        assert!(!either.contains(&Item::new('Z')));
+    }
+
+    #[test]
+    fn scoring_is_generic_over_the_priority_scheme() {
+        let rucksack = Rucksack::new("abéXcdéY");
+        assert_eq!(rucksack.score(&UnicodeLetters), Priority(158));
+
+        let table = "a=5\nb=7\nr=100\n".parse::<Table>().unwrap();
+        assert_eq!(Rucksack::new("abab").score(&table), Priority(12));
+
+        let group = Group(vec![
             Rucksack::new("vJrwpWtwJgWrhcsFMMfFFhFp"),
             Rucksack::new("jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL"),
             Rucksack::new("PmmdzqPrVvPwwTWBwg"),
         ]);
+        assert_eq!(group.score(&AsciiLetters), Ok(Priority(18)));
+        assert_eq!(group.score(&table), Ok(Priority(100)));
 
-        assert_eq!(group.badge(), Item::new('r'));
+        assert!(Rucksack::try_new_with("ßaßb", &UnicodeLetters).is_ok());
+        assert!(Rucksack::try_new("ßaßb").is_err());
+    }
+
+    #[test]
+    fn rucksacks_report_every_defect() {
+        assert!(Rucksack::try_new("vJrwpWtwJgWrhcsFMMfFFhFp").is_ok());
+
+        assert_eq!(
+            Rucksack::try_new("ab1Cd").unwrap_err(),
+            vec![
+                Defect {
+                    column: 3,
+                    kind: DefectKind::IllegalItem('1'),
+                },
+                Defect {
+                    column: 5,
+                    kind: DefectKind::OddLength(5),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parsing_reports_lines_and_incomplete_groups() {
+        let input = "vJrwpWtwJgWrhcsFMMfFFhFp\n\
+                     jqHRNqRj-zjGDLGLrsFMfFZSrLrFZsSL\n\
+                     PmmdzqPrVvPwwTWBwg\n\
+                     wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn\n";
+
+        assert_eq!(
+            parse_rucksacks(input).unwrap_err().0,
+            vec![
+                Diagnostic {
+                    line: 2,
+                    column: 9,
+                    kind: DefectKind::IllegalItem('-'),
+                },
+                Diagnostic {
+                    line: 4,
+                    column: 1,
+                    kind: DefectKind::IncompleteGroup {
+                        found: 1,
+                        expected: 3,
+                    },
+                },
+            ]
+        );
+
+        assert_eq!(
+            parse_rucksacks(&input.replace('-', "q"))
+                .unwrap_err()
+                .0
+                .len(),
+            1
+        );
     }
 }
@@ -0,0 +1,131 @@
+use crate::{priority::PriorityScheme, Rucksack};
+use std::{
+    fmt,
+    io::{self, BufRead},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum DefectKind {
+    IllegalItem(char),
+    OddLength(usize),
+    IncompleteGroup { found: usize, expected: usize },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct Defect {
+    pub(crate) column: usize,
+    pub(crate) kind: DefectKind,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) kind: DefectKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct ParseError(pub(crate) Vec<Diagnostic>);
+
+#[cfg(test)]
+pub(crate) fn parse_rucksacks(input: &str) -> Result<Vec<Rucksack>, ParseError> {
+    read_rucksacks_in_groups(
+        input.as_bytes(),
+        crate::DEFAULT_GROUP_SIZE,
+        &crate::priority::AsciiLetters,
+    )
+    .expect("in-memory input is valid UTF-8")
+}
+
+pub(crate) fn read_rucksacks_in_groups<R, S>(
+    reader: R,
+    group_size: usize,
+    scheme: &S,
+) -> io::Result<Result<Vec<Rucksack>, ParseError>>
+where
+    R: BufRead,
+    S: PriorityScheme + ?Sized,
+{
+    let mut rucksacks = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut lines = 0;
+
+    for items in reader.lines() {
+        lines += 1;
+        match parse_line(lines, &items?, scheme) {
+            Ok(rucksack) => rucksacks.push(rucksack),
+            Err(defects) => diagnostics.extend(defects),
+        }
+    }
+
+    diagnostics.extend(incomplete_group(lines, group_size));
+
+    if diagnostics.is_empty() {
+        Ok(Ok(rucksacks))
+    } else {
+        Ok(Err(ParseError(diagnostics)))
+    }
+}
+
+pub(crate) fn parse_line<S: PriorityScheme + ?Sized>(
+    line: usize,
+    items: &str,
+    scheme: &S,
+) -> Result<Rucksack, Vec<Diagnostic>> {
+    Rucksack::try_new_with(items, scheme).map_err(|defects| locate(line, defects))
+}
+
+fn locate(line: usize, defects: Vec<Defect>) -> Vec<Diagnostic> {
+    defects
+        .into_iter()
+        .map(|defect| Diagnostic {
+            line,
+            column: defect.column,
+            kind: defect.kind,
+        })
+        .collect()
+}
+
+pub(crate) fn incomplete_group(lines: usize, group_size: usize) -> Option<Diagnostic> {
+    let trailing = lines % group_size;
+    (trailing != 0).then_some(Diagnostic {
+        line: lines - trailing + 1,
+        column: 1,
+        kind: DefectKind::IncompleteGroup {
+            found: trailing,
+            expected: group_size,
+        },
+    })
+}
+
+impl fmt::Display for DefectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefectKind::IllegalItem(item) => write!(f, "illegal item {item:?}"),
+            DefectKind::OddLength(len) => {
+                write!(f, "odd number of items ({len}) cannot be split in two")
+            }
+            DefectKind::IncompleteGroup { found, expected } => {
+                write!(
+                    f,
+                    "incomplete group of {found} rucksack(s), expected {expected}"
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.0 {
+            writeln!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
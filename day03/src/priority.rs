@@ -0,0 +1,160 @@
+use crate::{
+    item_set::{CharSet, ItemSet, Items},
+    Item, Priority,
+};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+pub(crate) trait PriorityScheme {
+    // The set type that can hold every item the scheme accepts.
+    type Set: Items;
+
+    fn priority(&self, item: Item) -> Option<Priority>;
+
+    fn accepts(&self, item: char) -> bool {
+        item.is_alphabetic() && self.priority(Item(item)).is_some()
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct AsciiLetters;
+
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct Table(HashMap<char, Priority>);
+
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct UnicodeLetters;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct TableError {
+    pub(crate) line: usize,
+    pub(crate) text: String,
+}
+
+impl PriorityScheme for AsciiLetters {
+    type Set = ItemSet;
+
+    fn priority(&self, item: Item) -> Option<Priority> {
+        match item {
+            Item('a'..='z') => Some(Priority(item.0 as i32 - 'a' as i32 + 1)),
+            Item('A'..='Z') => Some(Priority(item.0 as i32 - 'A' as i32 + 27)),
+            _ => None,
+        }
+    }
+}
+
+impl PriorityScheme for Table {
+    type Set = CharSet;
+
+    fn priority(&self, item: Item) -> Option<Priority> {
+        self.0.get(&item.0).copied()
+    }
+
+    // A table may score items that are not letters at all.
+    fn accepts(&self, item: char) -> bool {
+        self.0.contains_key(&item)
+    }
+}
+
+// Non-ASCII letters rank after 'Z', in code point order.
+impl PriorityScheme for UnicodeLetters {
+    type Set = CharSet;
+
+    fn priority(&self, item: Item) -> Option<Priority> {
+        match AsciiLetters.priority(item) {
+            Some(priority) => Some(priority),
+            None if item.0.is_alphabetic() && !item.0.is_ascii() => {
+                Some(Priority(52 + item.0 as i32 - 0x7f))
+            }
+            None => None,
+        }
+    }
+}
+
+impl<S: PriorityScheme + ?Sized> PriorityScheme for Box<S> {
+    type Set = S::Set;
+
+    fn priority(&self, item: Item) -> Option<Priority> {
+        (**self).priority(item)
+    }
+
+    fn accepts(&self, item: char) -> bool {
+        (**self).accepts(item)
+    }
+}
+
+impl FromStr for Table {
+    type Err = TableError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut table = HashMap::new();
+
+        for (line, text) in (1..).zip(s.lines()) {
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let error = || TableError {
+                line,
+                text: text.to_owned(),
+            };
+
+            let (item, value) = text.split_once('=').ok_or_else(error)?;
+            let mut chars = item.trim().chars();
+            let item = match (chars.next(), chars.next()) {
+                (Some(item), None) => item,
+                _ => return Err(error()),
+            };
+            let value = value.trim().parse().map_err(|_| error())?;
+
+            table.insert(item, Priority(value));
+        }
+
+        Ok(Table(table))
+    }
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected `char=value`, found {:?}",
+            self.line, self.text
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tables_are_loaded_from_text() {
+        let table = "a=10\n\nZ = 3\né=7\n1=4\n".parse::<Table>().unwrap();
+
+        assert_eq!(table.priority(Item::new('a')), Some(Priority(10)));
+        assert_eq!(table.priority(Item::new('Z')), Some(Priority(3)));
+        assert_eq!(table.priority(Item::new('é')), Some(Priority(7)));
+        assert_eq!(table.priority(Item::new('b')), None);
+        assert!(!table.accepts('b'));
+        assert!(table.accepts('1'));
+        assert_eq!(table.priority(Item::new('1')), Some(Priority(4)));
+
+        assert_eq!(
+            "a=1\nbc=2\n".parse::<Table>(),
+            Err(TableError {
+                line: 2,
+                text: "bc=2".to_owned()
+            })
+        );
+        assert!("a=x".parse::<Table>().is_err());
+    }
+
+    #[test]
+    fn unicode_letters_extend_the_ascii_mapping() {
+        assert_eq!(UnicodeLetters.priority(Item::new('a')), Some(Priority(1)));
+        assert_eq!(UnicodeLetters.priority(Item::new('Z')), Some(Priority(52)));
+        assert_eq!(UnicodeLetters.priority(Item::new('ß')), Some(Priority(148)));
+        assert_eq!(AsciiLetters.priority(Item::new('ß')), None);
+        assert!(!UnicodeLetters.accepts('1'));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{item_set::Items, priority::PriorityScheme, Item, Priority, Rucksack};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct Swap {
+    pub(crate) left: usize,
+    pub(crate) right: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Rebalancing {
+    pub(crate) swaps: Vec<Swap>,
+    pub(crate) cost: Priority,
+}
+
+type Best = Option<(usize, i32)>;
+
+impl Rucksack {
+    pub(crate) fn rebalance<S: PriorityScheme + ?Sized>(&self, scheme: &S) -> Option<Rebalancing> {
+        let (left, right) = self.compartments();
+        let half = left.len();
+        let items = self.items::<S::Set>().iter().collect::<Vec<_>>();
+        let count = |compartment: &[Item], item: Item| {
+            compartment.iter().filter(|&&other| other == item).count()
+        };
+        let moving = |item: Item, count: usize| {
+            let priority = scheme.priority(item).expect("item outside priority scheme");
+            (count, count as i32 * priority.0)
+        };
+
+        let mut table: Vec<Vec<Best>> = vec![vec![None; half + 1]; items.len() + 1];
+        table[0][0] = Some((0, 0));
+
+        for (index, &item) in items.iter().enumerate() {
+            let (in_left, in_right) = (count(left, item), count(right, item));
+            for size in 0..=half {
+                let Some(best) = table[index][size] else {
+                    continue;
+                };
+
+                relax(&mut table[index + 1][size], best, moving(item, in_left));
+                if size + in_left + in_right <= half {
+                    relax(
+                        &mut table[index + 1][size + in_left + in_right],
+                        best,
+                        moving(item, in_right),
+                    );
+                }
+            }
+        }
+
+        let (_, weight) = table[items.len()][half]?;
+
+        let mut size = half;
+        let mut kept_left = S::Set::default();
+        for (index, &item) in items.iter().enumerate().rev() {
+            let (in_left, in_right) = (count(left, item), count(right, item));
+            let best = table[index + 1][size];
+            let via = |size: usize, (count, weight): (usize, i32)| {
+                table[index][size].map(|(moved, total)| (moved + count, total + weight))
+            };
+
+            if via(size, moving(item, in_left)) != best {
+                kept_left.insert(item);
+                size -= in_left + in_right;
+            }
+        }
+
+        let outgoing = (0..half).filter(|&i| !kept_left.contains(&left[i]));
+        let incoming = (0..half).filter(|&i| kept_left.contains(&right[i]));
+        let swaps = outgoing
+            .zip(incoming)
+            .map(|(left, right)| Swap {
+                left,
+                right: half + right,
+            })
+            .collect();
+
+        Some(Rebalancing {
+            swaps,
+            cost: Priority(weight),
+        })
+    }
+}
+
+fn relax(slot: &mut Best, (moved, total): (usize, i32), (count, weight): (usize, i32)) {
+    let candidate = (moved + count, total + weight);
+    if slot.is_none_or(|current| candidate < current) {
+        *slot = Some(candidate);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{item_set::ItemSet, priority::AsciiLetters};
+
+    fn apply(rucksack: &Rucksack, rebalancing: &Rebalancing) -> Rucksack {
+        let mut rucksack = rucksack.clone();
+        for swap in &rebalancing.swaps {
+            rucksack.0.swap(swap.left, swap.right);
+        }
+        rucksack
+    }
+
+    #[test]
+    fn rebalancing_removes_common_items() {
+        let rucksack = Rucksack::new("vJrwpWtwJgWrhcsFMMfFFhFp");
+        let rebalancing = rucksack.rebalance(&AsciiLetters).unwrap();
+
+        assert_eq!(rebalancing.swaps.len(), 1);
+        assert_eq!(rebalancing.cost, Priority(16 + 3));
+
+        let repaired = apply(&rucksack, &rebalancing);
+        assert_eq!(
+            repaired
+                .common_items_in_both_compartments::<ItemSet>()
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    fn every_example_rucksack_can_be_repaired() {
+        for items in [
+            "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+            "PmmdzqPrVvPwwTWBwg",
+            "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+            "ttgJtRGJQctTZtZT",
+            "CrZsJsPPZsGzwwsLwLmpwMDw",
+        ] {
+            let rucksack = Rucksack::new(items);
+            let rebalancing = rucksack.rebalance(&AsciiLetters).unwrap();
+            let repaired = apply(&rucksack, &rebalancing);
+            assert_eq!(
+                repaired
+                    .common_items_in_both_compartments::<ItemSet>()
+                    .len(),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn rebalancing_prefers_cheap_items() {
+        let rucksack = Rucksack::new("aZbZ");
+        let rebalancing = rucksack.rebalance(&AsciiLetters).unwrap();
+
+        assert_eq!(rebalancing.swaps, vec![Swap { left: 0, right: 3 }]);
+        assert_eq!(rebalancing.cost, Priority(1 + 52));
+    }
+
+    #[test]
+    fn balanced_rucksacks_need_no_swaps() {
+        let rebalancing = Rucksack::new("abcd").rebalance(&AsciiLetters).unwrap();
+        assert_eq!(rebalancing.swaps, vec![]);
+        assert_eq!(rebalancing.cost, Priority(0));
+    }
+
+    #[test]
+    fn some_rucksacks_cannot_be_rebalanced() {
+        assert_eq!(Rucksack::new("aaab").rebalance(&AsciiLetters), None);
+    }
+}
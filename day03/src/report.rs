@@ -0,0 +1,306 @@
+use crate::{item_set::Items, priority::PriorityScheme, Badge, Group, Item, Priority, Rucksack};
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Format {
+    JsonLines,
+    Csv,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Record {
+    Rucksack {
+        line: usize,
+        duplicates: Vec<(Item, Priority)>,
+    },
+    Group {
+        index: usize,
+        lines: Vec<usize>,
+        badge: Option<(Item, Priority)>,
+    },
+    Total {
+        part: usize,
+        priority: Priority,
+    },
+}
+
+pub(crate) fn write_report<W, S>(
+    out: &mut W,
+    format: Format,
+    rucksacks: &[Rucksack],
+    groups: &[(Vec<usize>, Group)],
+    scheme: &S,
+) -> io::Result<()>
+where
+    W: Write,
+    S: PriorityScheme + ?Sized,
+{
+    let scored = |item: Item| (item, scheme.priority(item).unwrap());
+
+    let rucksacks = rucksacks
+        .iter()
+        .zip(1..)
+        .map(|(rucksack, line)| Record::Rucksack {
+            line,
+            duplicates: rucksack
+                .common_items_in_both_compartments::<S::Set>()
+                .iter()
+                .map(scored)
+                .collect(),
+        });
+
+    let groups = groups
+        .iter()
+        .zip(1..)
+        .map(|((members, group), index)| Record::Group {
+            index,
+            lines: members.iter().map(|member| member + 1).collect(),
+            badge: match group.badge::<S::Set>() {
+                Badge::Unique(item) => Some(scored(item)),
+                _ => None,
+            },
+        });
+
+    let mut totals = [Priority(0), Priority(0)];
+
+    if format == Format::Csv {
+        writeln!(out, "record,part,group,lines,items,priorities,priority")?;
+    }
+
+    for record in rucksacks.chain(groups) {
+        match &record {
+            Record::Rucksack { duplicates, .. } => totals[0].0 += total(duplicates).0,
+            Record::Group {
+                badge: Some((_, priority)),
+                ..
+            } => totals[1].0 += priority.0,
+            _ => {}
+        }
+        record.write(out, format)?;
+    }
+
+    for (part, priority) in (1..).zip(totals) {
+        Record::Total { part, priority }.write(out, format)?;
+    }
+
+    Ok(())
+}
+
+impl Record {
+    fn write<W: Write>(&self, out: &mut W, format: Format) -> io::Result<()> {
+        match format {
+            Format::JsonLines => self.write_json(out),
+            Format::Csv => self.write_csv(out),
+        }
+    }
+
+    fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self {
+            Record::Rucksack { line, duplicates } => writeln!(
+                out,
+                r#"{{"record":"rucksack","line":{line},"duplicates":[{}],"priority":{}}}"#,
+                duplicates
+                    .iter()
+                    .map(|(item, priority)| format!(
+                        r#"{{"item":{},"priority":{}}}"#,
+                        json_string(*item),
+                        priority.0
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                total(duplicates).0
+            ),
+            Record::Group {
+                index,
+                lines,
+                badge,
+            } => writeln!(
+                out,
+                r#"{{"record":"group","group":{index},"lines":[{}],"badge":{},"priority":{}}}"#,
+                join(lines, ","),
+                badge.map_or("null".to_owned(), |(item, _)| json_string(item)),
+                badge.map_or("null".to_owned(), |(_, priority)| priority.0.to_string()),
+            ),
+            Record::Total { part, priority } => writeln!(
+                out,
+                r#"{{"record":"total","part":{part},"priority":{}}}"#,
+                priority.0
+            ),
+        }
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self {
+            Record::Rucksack { line, duplicates } => writeln!(
+                out,
+                "rucksack,1,,{line},{},{},{}",
+                csv_field(
+                    &duplicates
+                        .iter()
+                        .map(|(item, _)| item.0)
+                        .collect::<String>()
+                ),
+                join(duplicates.iter().map(|(_, priority)| priority.0), ";"),
+                total(duplicates).0
+            ),
+            Record::Group {
+                index,
+                lines,
+                badge,
+            } => writeln!(
+                out,
+                "group,2,{index},{},{},{},{}",
+                join(lines, ";"),
+                badge.map_or(String::new(), |(item, _)| csv_field(&item.0.to_string())),
+                badge.map_or(String::new(), |(_, priority)| priority.0.to_string()),
+                badge.map_or(String::new(), |(_, priority)| priority.0.to_string()),
+            ),
+            Record::Total { part, priority } => {
+                writeln!(out, "total,{part},,,,,{}", priority.0)
+            }
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" | "jsonl" => Ok(Format::JsonLines),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown report format {s:?}, expected json or csv")),
+        }
+    }
+}
+
+fn total(duplicates: &[(Item, Priority)]) -> Priority {
+    duplicates.iter().map(|(_, priority)| *priority).sum()
+}
+
+fn json_string(item: Item) -> String {
+    match item.0 {
+        '"' => r#""\"""#.to_owned(),
+        '\\' => r#""\\""#.to_owned(),
+        c if c.is_control() => format!(r#""\u{:04x}""#, c as u32),
+        c => format!(r#""{c}""#),
+    }
+}
+
+// Fields holding a separator, quote or line break are quoted, with quotes
+// doubled inside.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!(r#""{}""#, text.replace('"', r#""""#))
+    } else {
+        text.to_owned()
+    }
+}
+
+fn join<T: ToString>(values: impl IntoIterator<Item = T>, separator: &str) -> String {
+    values
+        .into_iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        groups,
+        priority::{AsciiLetters, Table},
+    };
+
+    fn report(format: Format) -> String {
+        let rucksacks = [
+            "vJrwpWtwJgWrhcsFMMfFFhFp",
+            "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+            "PmmdzqPrVvPwwTWBwg",
+            "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+            "ttgJtRGJQctTZtZT",
+            "CrZsJsPPZsGzwwsLwLmpwMDw",
+        ]
+        .map(Rucksack::new);
+        let groups = groups(&rucksacks, 3)
+            .zip([vec![0, 1, 2], vec![3, 4, 5]])
+            .map(|(group, members)| (members, group))
+            .collect::<Vec<_>>();
+
+        let mut out = Vec::new();
+        write_report(&mut out, format, &rucksacks, &groups, &AsciiLetters).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json_lines_report_matches_the_example() {
+        let report = report(Format::JsonLines);
+        let lines = report.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 10);
+        assert_eq!(
+            lines[0],
+            r#"{"record":"rucksack","line":1,"duplicates":[{"item":"p","priority":16}],"priority":16}"#
+        );
+        assert_eq!(
+            lines[6],
+            r#"{"record":"group","group":1,"lines":[1,2,3],"badge":"r","priority":18}"#
+        );
+        assert_eq!(lines[8], r#"{"record":"total","part":1,"priority":157}"#);
+        assert_eq!(lines[9], r#"{"record":"total","part":2,"priority":70}"#);
+    }
+
+    #[test]
+    fn csv_report_matches_the_example() {
+        let report = report(Format::Csv);
+        let lines = report.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[0],
+            "record,part,group,lines,items,priorities,priority"
+        );
+        assert_eq!(lines[2], "rucksack,1,,2,L,38,38");
+        assert_eq!(lines[8], "group,2,2,4;5;6,Z,52,52");
+        assert_eq!(lines[9], "total,1,,,,,157");
+        assert_eq!(lines[10], "total,2,,,,,70");
+    }
+
+    #[test]
+    fn reports_escape_items() {
+        let table = "\"=1\n,=2\n\\=3\nb=4\n".parse::<Table>().unwrap();
+        let rucksacks = ["\",\",", "b\\b\\", "\"\"bb"].map(Rucksack::new);
+        let groups = groups(&rucksacks, 3)
+            .map(|group| (vec![0, 1, 2], group))
+            .collect::<Vec<_>>();
+
+        let report = |format| {
+            let mut out = Vec::new();
+            write_report(&mut out, format, &rucksacks, &groups, &table).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let json = report(Format::JsonLines);
+        let lines = json.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            r#"{"record":"rucksack","line":1,"duplicates":[{"item":"\"","priority":1},{"item":",","priority":2}],"priority":3}"#
+        );
+        assert_eq!(
+            lines[1],
+            r#"{"record":"rucksack","line":2,"duplicates":[{"item":"\\","priority":3},{"item":"b","priority":4}],"priority":7}"#
+        );
+        assert_eq!(
+            lines[3],
+            r#"{"record":"group","group":1,"lines":[1,2,3],"badge":null,"priority":null}"#
+        );
+
+        let csv = report(Format::Csv);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], r#"rucksack,1,,1,""",",1;2,3"#);
+        assert_eq!(lines[2], r"rucksack,1,,2,\b,3;4,7");
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{item_set::Items, Item, Rucksack};
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum NoPartition {
+    IndivisibleCount { rucksacks: usize, group_size: usize },
+    Isolated { rucksack: usize },
+    Uncovered { rucksack: usize, refuted: usize },
+}
+
+// Every group has exactly one badge, so the groups a rucksack can join are
+// found item by item among the other rucksacks holding that item, and each is
+// found once. Each rucksack's count of groups among the unplaced rucksacks is
+// kept up to date as groups are placed and taken back, and the search always
+// places the rucksack with the fewest, so when it fails at the top that
+// rucksack is the one no partition can cover.
+struct Search<'a, T> {
+    sets: &'a [T],
+    size: usize,
+    holders: HashMap<Item, Vec<usize>>,
+    assigned: Vec<bool>,
+    counts: Vec<usize>,
+    groups: Vec<Vec<usize>>,
+}
+
+pub(crate) fn partition_into_groups<T: Items>(
+    rucksacks: &[Rucksack],
+    size: usize,
+) -> Result<Vec<Vec<usize>>, NoPartition> {
+    assert!(size > 0);
+
+    if !rucksacks.len().is_multiple_of(size) {
+        return Err(NoPartition::IndivisibleCount {
+            rucksacks: rucksacks.len(),
+            group_size: size,
+        });
+    }
+
+    let sets = rucksacks
+        .iter()
+        .map(Rucksack::items::<T>)
+        .collect::<Vec<_>>();
+
+    let mut holders = HashMap::<Item, Vec<usize>>::new();
+    for (rucksack, set) in sets.iter().enumerate() {
+        for item in set.iter() {
+            holders.entry(item).or_default().push(rucksack);
+        }
+    }
+
+    let mut search = Search {
+        sets: &sets,
+        size,
+        holders,
+        assigned: vec![false; sets.len()],
+        counts: vec![0; sets.len()],
+        groups: Vec::new(),
+    };
+    for rucksack in 0..sets.len() {
+        let mut count = 0;
+        search.groups_with(rucksack, &mut |_| count += 1);
+        search.counts[rucksack] = count;
+    }
+
+    let Some((rucksack, candidates)) = search.most_constrained() else {
+        return Ok(Vec::new());
+    };
+    if candidates.is_empty() {
+        return Err(NoPartition::Isolated { rucksack });
+    }
+    if search.place(&candidates) {
+        Ok(search.groups)
+    } else {
+        Err(NoPartition::Uncovered {
+            rucksack,
+            refuted: candidates.len(),
+        })
+    }
+}
+
+impl<T: Items> Search<'_, T> {
+    fn solve(&mut self) -> bool {
+        match self.most_constrained() {
+            None => true,
+            Some((_, candidates)) => self.place(&candidates),
+        }
+    }
+
+    fn place(&mut self, candidates: &[Vec<usize>]) -> bool {
+        for group in candidates {
+            self.assign(group);
+            self.groups.push(group.clone());
+
+            if self.solve() {
+                return true;
+            }
+
+            self.groups.pop();
+            self.release(group);
+        }
+
+        false
+    }
+
+    fn most_constrained(&self) -> Option<(usize, Vec<Vec<usize>>)> {
+        let rucksack = (0..self.sets.len())
+            .filter(|&rucksack| !self.assigned[rucksack])
+            .min_by_key(|&rucksack| self.counts[rucksack])?;
+
+        let mut candidates = Vec::new();
+        self.groups_with(rucksack, &mut |group| candidates.push(group.to_vec()));
+        Some((rucksack, candidates))
+    }
+
+    // Members are taken one at a time so a group holding several of them is
+    // only uncounted once, and released in reverse to count it back.
+    fn assign(&mut self, group: &[usize]) {
+        for &member in group {
+            self.recount(member, |count| *count -= 1);
+            self.assigned[member] = true;
+        }
+    }
+
+    fn release(&mut self, group: &[usize]) {
+        for &member in group.iter().rev() {
+            self.assigned[member] = false;
+            self.recount(member, |count| *count += 1);
+        }
+    }
+
+    fn recount(&mut self, rucksack: usize, change: impl Fn(&mut usize)) {
+        let mut members = Vec::new();
+        self.groups_with(rucksack, &mut |group| members.extend_from_slice(group));
+        for member in members {
+            change(&mut self.counts[member]);
+        }
+    }
+
+    fn groups_with(&self, rucksack: usize, found: &mut impl FnMut(&[usize])) {
+        let mut group = vec![rucksack];
+
+        for badge in self.sets[rucksack].iter() {
+            let partners = &self.holders[&badge];
+            let shared = self.sets[rucksack].clone();
+            self.extend(&mut group, shared, partners, found);
+        }
+    }
+
+    fn extend(
+        &self,
+        group: &mut Vec<usize>,
+        shared: T,
+        partners: &[usize],
+        found: &mut impl FnMut(&[usize]),
+    ) {
+        if group.len() == self.size {
+            if shared.len() == 1 {
+                found(group);
+            }
+            return;
+        }
+
+        for (index, &partner) in partners.iter().enumerate() {
+            if partner == group[0] || self.assigned[partner] {
+                continue;
+            }
+
+            group.push(partner);
+            let shared = shared.clone() & self.sets[partner].clone();
+            self.extend(group, shared, &partners[index + 1..], found);
+            group.pop();
+        }
+    }
+}
+
+impl fmt::Display for NoPartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoPartition::IndivisibleCount {
+                rucksacks,
+                group_size,
+            } => write!(
+                f,
+                "{rucksacks} rucksacks cannot be split into groups of {group_size}"
+            ),
+            NoPartition::Isolated { rucksack } => write!(
+                f,
+                "rucksack on line {} shares a unique badge with no possible group",
+                rucksack + 1
+            ),
+            NoPartition::Uncovered { rucksack, refuted } => write!(
+                f,
+                "rucksack on line {} is in no partition, each of its {refuted} possible groups leaves the rest unpartitionable",
+                rucksack + 1
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{item_set::ItemSet, Badge};
+
+    const EXAMPLE: [&str; 6] = [
+        "vJrwpWtwJgWrhcsFMMfFFhFp",
+        "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+        "PmmdzqPrVvPwwTWBwg",
+        "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+        "ttgJtRGJQctTZtZT",
+        "CrZsJsPPZsGzwwsLwLmpwMDw",
+    ];
+
+    #[test]
+    fn shuffled_example_is_partitioned_by_badge() {
+        let rucksacks = [4, 0, 3, 2, 5, 1].map(|i| Rucksack::new(EXAMPLE[i]));
+
+        let partition = partition_into_groups::<ItemSet>(&rucksacks, 3).unwrap();
+        assert_eq!(partition.len(), 2);
+
+        let mut members = partition.concat();
+        members.sort();
+        assert_eq!(members, (0..6).collect::<Vec<_>>());
+
+        for group in partition {
+            let sets = group
+                .iter()
+                .map(|&i| rucksacks[i].items::<ItemSet>())
+                .collect::<Vec<_>>();
+            assert!(matches!(Badge::of(&sets), Badge::Unique(_)));
+        }
+    }
+
+    #[test]
+    fn impossible_partitions_are_explained() {
+        let rucksacks = EXAMPLE.map(Rucksack::new);
+
+        assert_eq!(
+            partition_into_groups::<ItemSet>(&rucksacks[..5], 3),
+            Err(NoPartition::IndivisibleCount {
+                rucksacks: 5,
+                group_size: 3
+            })
+        );
+
+        let mut isolated = rucksacks.to_vec();
+        isolated[3] = Rucksack::new("xxyy");
+        assert_eq!(
+            partition_into_groups::<ItemSet>(&isolated, 3),
+            Err(NoPartition::Isolated { rucksack: 3 })
+        );
+
+        let rucksacks = ["ax", "acdw", "cy", "dz"].map(Rucksack::new);
+        assert_eq!(
+            partition_into_groups::<ItemSet>(&rucksacks, 2),
+            Err(NoPartition::Uncovered {
+                rucksack: 0,
+                refuted: 1
+            })
+        );
+    }
+
+    #[test]
+    fn shuffled_input_is_partitioned_quickly() {
+        let lines = include_str!("input.txt")
+            .lines()
+            .take(150)
+            .collect::<Vec<_>>();
+        let rucksacks = (0..lines.len())
+            .map(|i| Rucksack::new(lines[i * 7 % lines.len()]))
+            .collect::<Vec<_>>();
+
+        let partition = partition_into_groups::<ItemSet>(&rucksacks, 3).unwrap();
+        assert_eq!(partition.len(), 50);
+    }
+}
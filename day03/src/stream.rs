@@ -0,0 +1,169 @@
+use crate::{
+    item_set::{ItemSet, Items},
+    parsing::{incomplete_group, parse_line, Diagnostic},
+    priority::PriorityScheme,
+    Badge, Group, Priority,
+};
+use std::{
+    fmt,
+    io::{self, BufRead},
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Unresolved<T = ItemSet> {
+    pub(crate) group: usize,
+    pub(crate) members: Vec<usize>,
+    pub(crate) badge: Badge<T>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Summary<T = ItemSet> {
+    pub(crate) part_1: Priority,
+    pub(crate) part_2: Priority,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    pub(crate) unresolved: Vec<Unresolved<T>>,
+}
+
+pub(crate) fn summarise<R, S>(
+    mut reader: R,
+    group_size: usize,
+    scheme: &S,
+) -> io::Result<Summary<S::Set>>
+where
+    R: BufRead,
+    S: PriorityScheme + ?Sized,
+{
+    assert!(group_size > 0);
+
+    let mut summary = Summary {
+        part_1: Priority(0),
+        part_2: Priority(0),
+        diagnostics: Vec::new(),
+        unresolved: Vec::new(),
+    };
+
+    let mut buffer = String::new();
+    let mut group = Vec::with_capacity(group_size);
+    let mut complete = true;
+    let mut lines = 0;
+
+    while reader.read_line(&mut buffer)? > 0 {
+        lines += 1;
+
+        let items = buffer.trim_end_matches(['\n', '\r']);
+        match parse_line(lines, items, scheme) {
+            Ok(rucksack) => {
+                summary.part_1.0 += rucksack.score(scheme).0;
+                group.push(rucksack);
+            }
+            Err(diagnostics) => {
+                summary.diagnostics.extend(diagnostics);
+                complete = false;
+            }
+        }
+        buffer.clear();
+
+        if lines % group_size == 0 {
+            let rucksacks = std::mem::replace(&mut group, Vec::with_capacity(group_size));
+            if complete {
+                match Group(rucksacks).score(scheme) {
+                    Ok(priority) => summary.part_2.0 += priority.0,
+                    Err(badge) => summary.unresolved.push(Unresolved {
+                        group: lines / group_size,
+                        members: (lines - group_size..lines).collect(),
+                        badge,
+                    }),
+                }
+            }
+            complete = true;
+        }
+    }
+
+    summary
+        .diagnostics
+        .extend(incomplete_group(lines, group_size));
+
+    Ok(summary)
+}
+
+impl<T: Items> fmt::Display for Unresolved<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = |rucksacks: &[usize]| {
+            rucksacks
+                .iter()
+                .map(|&rucksack| self.members[rucksack] + 1)
+                .collect::<Vec<_>>()
+        };
+
+        match &self.badge {
+            Badge::Unique(item) => write!(f, "group {}: badge {:?}", self.group, item.0),
+            Badge::Missing { rucksacks } => write!(
+                f,
+                "group {}: no shared badge, check lines {:?}",
+                self.group,
+                lines(rucksacks)
+            ),
+            Badge::Ambiguous {
+                candidates,
+                rucksacks,
+            } => write!(
+                f,
+                "group {}: {} candidate badges {:?} on lines {:?}",
+                self.group,
+                candidates.len(),
+                candidates.iter().map(|item| item.0).collect::<String>(),
+                lines(rucksacks)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parsing::DefectKind, priority::AsciiLetters, Item};
+
+    const EXAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw
+";
+
+    #[test]
+    fn streaming_matches_the_example_totals() {
+        let summary = summarise(EXAMPLE.as_bytes(), 3, &AsciiLetters).unwrap();
+
+        assert_eq!(summary.part_1, Priority(157));
+        assert_eq!(summary.part_2, Priority(70));
+        assert_eq!(summary.diagnostics, vec![]);
+        assert_eq!(summary.unresolved, vec![]);
+    }
+
+    #[test]
+    fn streaming_reports_defects_and_unresolved_groups() {
+        let input = EXAMPLE.replace("ttgJ", "tt1J") + "ab\r\nab\r\nab\r\n";
+        let summary = summarise(input.as_bytes(), 3, &AsciiLetters).unwrap();
+
+        assert_eq!(
+            summary.diagnostics,
+            vec![Diagnostic {
+                line: 5,
+                column: 3,
+                kind: DefectKind::IllegalItem('1'),
+            }]
+        );
+        assert_eq!(
+            summary.unresolved,
+            vec![Unresolved {
+                group: 3,
+                members: vec![6, 7, 8],
+                badge: Badge::Ambiguous {
+                    candidates: ItemSet::from_iter([Item::new('a'), Item::new('b')]),
+                    rucksacks: vec![0, 1, 2],
+                },
+            }]
+        );
+    }
+}
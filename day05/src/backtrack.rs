@@ -0,0 +1,126 @@
+use crate::{
+    cranes::check,
+    drawing::{crate_at, stack_heights},
+    parse_operation, parse_operations, Error, NO_CRATE,
+};
+
+// Follows each final top position back through the log to the crate that
+// started there. Only the stack heights are simulated, to report the same
+// errors as a full rearrangement would, and the drawing is only read again
+// for the crates that end up on top.
+pub(crate) fn top_crates_by_backtracking(
+    input: &str,
+    preserve_order: bool,
+) -> Result<String, Error> {
+    let start = stack_heights(input)?;
+
+    let mut heights = start.clone();
+    for operation in parse_operations(input) {
+        let operation = operation?;
+        let remaining = check(operation, heights.len(), |stack| heights[stack])?;
+        heights[operation.from] = remaining;
+        heights[operation.to] += operation.count;
+    }
+
+    let lines = input.lines().count();
+    let mut top_crates = String::new();
+
+    for (stack, &height) in heights.iter().enumerate() {
+        if height == 0 {
+            top_crates.push_str(NO_CRATE);
+            continue;
+        }
+
+        let (mut stack, mut depth) = (stack, 0);
+        for (line, text) in (1..=lines)
+            .rev()
+            .zip(input.lines().rev())
+            .take_while(|(_, text)| !text.is_empty())
+        {
+            let operation = parse_operation(line, text)?;
+            if stack == operation.from && stack == operation.to {
+                if depth < operation.count && !preserve_order {
+                    depth = operation.count - 1 - depth;
+                }
+            } else if stack == operation.to {
+                if depth < operation.count {
+                    stack = operation.from;
+                    if !preserve_order {
+                        depth = operation.count - 1 - depth;
+                    }
+                } else {
+                    depth -= operation.count;
+                }
+            } else if stack == operation.from {
+                depth += operation.count;
+            }
+        }
+
+        let label = crate_at(input, stack, start[stack] - 1 - depth)
+            .expect("a crate that ends on top was in the drawing");
+        top_crates.push_str(&label);
+    }
+
+    Ok(top_crates)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{cranes::parse_crane, get_top_crates_following_rearrangement, synthetic::generate};
+
+    #[test]
+    fn matches_the_full_rearrangement() {
+        for seed in 1..50 {
+            let input = generate(4, 5, 100, 7, seed);
+            for (model, preserve_order) in [("9000", false), ("9001", true)] {
+                let mut crane = parse_crane(model).unwrap();
+                assert_eq!(
+                    top_crates_by_backtracking(&input, preserve_order),
+                    get_top_crates_following_rearrangement(&input, &mut crane),
+                    "seed {seed}, {model}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn follows_crates_put_back_on_their_own_stack() {
+        let input =
+            "[A]    \n[B]    \n[C] [D]\n 1   2 \n\nmove 1 from 1 to 1\nmove 1 from 1 to 2\n";
+        for (model, preserve_order) in [("9000", false), ("9001", true)] {
+            let mut crane = parse_crane(model).unwrap();
+            assert_eq!(
+                top_crates_by_backtracking(input, preserve_order),
+                Ok("BA".to_owned())
+            );
+            assert_eq!(
+                get_top_crates_following_rearrangement(input, &mut crane),
+                Ok("BA".to_owned())
+            );
+        }
+
+        let input = input.replace("move 1 from 1 to 1", "move 2 from 1 to 1");
+        let mut crane = parse_crane("9000").unwrap();
+        assert_eq!(
+            top_crates_by_backtracking(&input, false),
+            get_top_crates_following_rearrangement(&input, &mut crane)
+        );
+    }
+
+    #[test]
+    fn reports_the_same_errors() {
+        let input = "[A]    \n[B] [C]\n 1   2 \n\nmove 1 from 1 to 2\nmove 3 from 2 to 1\n";
+        let mut crane = parse_crane("9000").unwrap();
+        assert_eq!(
+            top_crates_by_backtracking(input, false),
+            get_top_crates_following_rearrangement(input, &mut crane)
+        );
+
+        let input = input.replace("move 3", "move 3 crates");
+        assert_eq!(
+            top_crates_by_backtracking(&input, true),
+            get_top_crates_following_rearrangement(&input, &mut crane)
+        );
+    }
+}
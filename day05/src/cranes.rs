@@ -0,0 +1,222 @@
+use crate::{Crate, MoveError, MoveErrorKind, Operation, Stacks};
+
+pub(crate) trait MoveCrates {
+    fn move_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError>;
+
+    // Undoes the most recent `move_crates`, rewinding any crane state with it.
+    fn unmove_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError>;
+}
+
+impl<C: MoveCrates + ?Sized> MoveCrates for Box<C> {
+    fn move_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError> {
+        (**self).move_crates(stacks, operation)
+    }
+
+    fn unmove_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError> {
+        (**self).unmove_crates(stacks, operation)
+    }
+}
+
+pub(crate) fn unapply_operations<C>(
+    stacks: Stacks,
+    operations: &[Operation],
+    crane: &mut C,
+) -> Result<Stacks, MoveError>
+where
+    C: MoveCrates + ?Sized,
+{
+    operations
+        .iter()
+        .rev()
+        .try_fold(stacks, |stacks, &operation| {
+            crane.unmove_crates(stacks, operation)
+        })
+}
+
+fn lift(stacks: &mut Stacks, operation: Operation) -> Result<Vec<Crate>, MoveError> {
+    let remaining = check(operation, stacks.len(), |stack| stacks[stack].len())?;
+    Ok(stacks[operation.from].drain(remaining..).collect())
+}
+
+// Returns how many crates stay behind on the source stack.
+pub(crate) fn check(
+    operation: Operation,
+    stacks: usize,
+    height: impl Fn(usize) -> usize,
+) -> Result<usize, MoveError> {
+    let error = |kind| MoveError {
+        line: operation.line,
+        kind,
+    };
+
+    for stack in [operation.from, operation.to] {
+        if stack >= stacks {
+            return Err(error(MoveErrorKind::NoSuchStack {
+                stack: stack + 1,
+                stacks,
+            }));
+        }
+    }
+
+    let crates_on_source_stack = height(operation.from);
+    crates_on_source_stack
+        .checked_sub(operation.count)
+        .ok_or_else(|| {
+            error(MoveErrorKind::NotEnoughCrates {
+                stack: operation.from + 1,
+                requested: operation.count,
+                available: crates_on_source_stack,
+            })
+        })
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct CrateMover9000;
+
+impl MoveCrates for CrateMover9000 {
+    fn move_crates(
+        &mut self,
+        mut stacks: Stacks,
+        operation: Operation,
+    ) -> Result<Stacks, MoveError> {
+        let crates = lift(&mut stacks, operation)?;
+        stacks[operation.to].extend(crates.into_iter().rev());
+        Ok(stacks)
+    }
+
+    fn unmove_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError> {
+        self.move_crates(stacks, operation.reversed())
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct CrateMover9001;
+
+impl MoveCrates for CrateMover9001 {
+    fn move_crates(
+        &mut self,
+        mut stacks: Stacks,
+        operation: Operation,
+    ) -> Result<Stacks, MoveError> {
+        let crates = lift(&mut stacks, operation)?;
+        stacks[operation.to].extend(crates);
+        Ok(stacks)
+    }
+
+    fn unmove_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError> {
+        self.move_crates(stacks, operation.reversed())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct LimitedGrab {
+    max_per_grab: usize,
+}
+
+impl LimitedGrab {
+    pub(crate) fn new(max_per_grab: usize) -> Self {
+        assert!(max_per_grab > 0);
+        Self { max_per_grab }
+    }
+}
+
+impl MoveCrates for LimitedGrab {
+    fn move_crates(
+        &mut self,
+        mut stacks: Stacks,
+        operation: Operation,
+    ) -> Result<Stacks, MoveError> {
+        let crates = lift(&mut stacks, operation)?;
+        for grab in crates.rchunks(self.max_per_grab) {
+            stacks[operation.to].extend_from_slice(grab);
+        }
+        Ok(stacks)
+    }
+
+    fn unmove_crates(
+        &mut self,
+        mut stacks: Stacks,
+        operation: Operation,
+    ) -> Result<Stacks, MoveError> {
+        let placed = lift(&mut stacks, operation.reversed())?;
+        for grab in placed.chunks(self.max_per_grab).rev() {
+            stacks[operation.from].extend_from_slice(grab);
+        }
+        Ok(stacks)
+    }
+}
+
+// Lifts the crates in order, but inverts every other crate with the one below
+// it, working down from the top: a lift of A B C D E (top last) lands as
+// A C B E D. Doing that twice restores the order, so undoing a move is the
+// same move in reverse.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct Inverting;
+
+impl MoveCrates for Inverting {
+    fn move_crates(
+        &mut self,
+        mut stacks: Stacks,
+        operation: Operation,
+    ) -> Result<Stacks, MoveError> {
+        let mut crates = lift(&mut stacks, operation)?;
+        for pair in crates.rchunks_exact_mut(2) {
+            pair.swap(0, 1);
+        }
+        stacks[operation.to].extend(crates);
+        Ok(stacks)
+    }
+
+    fn unmove_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError> {
+        self.move_crates(stacks, operation.reversed())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct Wearing<C> {
+    crane: C,
+    lifts: usize,
+    limit: usize,
+}
+
+impl<C> Wearing<C> {
+    pub(crate) fn new(crane: C, limit: usize) -> Self {
+        Self {
+            crane,
+            lifts: 0,
+            limit,
+        }
+    }
+}
+
+impl<C: MoveCrates> MoveCrates for Wearing<C> {
+    fn move_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError> {
+        if self.lifts == self.limit {
+            return Err(MoveError {
+                line: operation.line,
+                kind: MoveErrorKind::WornOut { lifts: self.lifts },
+            });
+        }
+        self.lifts += 1;
+        self.crane.move_crates(stacks, operation)
+    }
+
+    fn unmove_crates(&mut self, stacks: Stacks, operation: Operation) -> Result<Stacks, MoveError> {
+        self.lifts = self.lifts.saturating_sub(1);
+        self.crane.unmove_crates(stacks, operation)
+    }
+}
+
+pub(crate) fn parse_crane(model: &str) -> Option<Box<dyn MoveCrates>> {
+    match model {
+        "9000" => Some(Box::new(CrateMover9000)),
+        "9001" => Some(Box::new(CrateMover9001)),
+        "inverting" => Some(Box::new(Inverting)),
+        _ => Some(Box::new(parse_limited_grab(model)?)),
+    }
+}
+
+pub(crate) fn parse_limited_grab(model: &str) -> Option<LimitedGrab> {
+    let max_per_grab = model.strip_prefix("grab:")?.parse().ok()?;
+    (max_per_grab > 0).then(|| LimitedGrab::new(max_per_grab))
+}
@@ -0,0 +1,379 @@
+use crate::Stacks;
+use std::fmt;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum DrawingErrorKind {
+    MissingFooter,
+    UnexpectedStackNumber { expected: usize, found: String },
+    UnexpectedCharacter(char),
+    UnterminatedCrate,
+    EmptyCrate,
+    UnalignedCrate(String),
+    FloatingCrate(String),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct DrawingError {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) kind: DrawingErrorKind,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    fn overlaps(&self, other: &Span) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+pub(crate) fn parse_start_stacks(input: &str) -> Result<Stacks, DrawingError> {
+    let mut stacks: Stacks = Vec::new();
+    let heights = scan(input, |stack, label| {
+        if stacks.len() <= stack {
+            stacks.resize(stack + 1, Vec::new());
+        }
+        stacks[stack].push(label);
+    })?;
+    stacks.resize(heights.len(), Vec::new());
+    Ok(stacks)
+}
+
+pub(crate) fn stack_heights(input: &str) -> Result<Vec<usize>, DrawingError> {
+    scan(input, |_, _| {})
+}
+
+// Reads a single crate out of a drawing that has already been validated,
+// counting levels up from the bottom of the stack.
+pub(crate) fn crate_at(input: &str, stack: usize, level: usize) -> Option<String> {
+    let drawing = input
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    let (footer, rows) = drawing.split_last()?;
+
+    let column = *parse_footer(rows.len() + 1, footer).ok()?.get(stack)?;
+    let line = rows.len().checked_sub(level + 1)?;
+    parse_row(line + 1, rows[line])
+        .ok()?
+        .into_iter()
+        .find(|(span, _)| span.overlaps(&column))
+        .map(|(_, label)| label)
+}
+
+// Validates the drawing and hands each crate to `place` from the bottom up,
+// returning the height of every stack.
+fn scan(input: &str, mut place: impl FnMut(usize, String)) -> Result<Vec<usize>, DrawingError> {
+    let drawing = (1..)
+        .zip(input.lines())
+        .take_while(|(_, line)| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    let Some((&(footer_line, footer), rows)) = drawing.split_last() else {
+        return Err(DrawingError {
+            line: 1,
+            column: 1,
+            kind: DrawingErrorKind::MissingFooter,
+        });
+    };
+
+    let columns = parse_footer(footer_line, footer)?;
+
+    rows.iter().rev().enumerate().try_fold(
+        vec![0; columns.len()],
+        |mut heights, (level, &(line, row))| {
+            let error = |column, kind| DrawingError { line, column, kind };
+
+            let mut filled = vec![false; columns.len()];
+            for (span, label) in parse_row(line, row)? {
+                let mut matching = columns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, column)| column.overlaps(&span))
+                    .map(|(stack, _)| stack);
+
+                let stack = match (matching.next(), matching.next()) {
+                    (Some(stack), None) if !filled[stack] => stack,
+                    _ => {
+                        return Err(error(
+                            span.start + 1,
+                            DrawingErrorKind::UnalignedCrate(label),
+                        ))
+                    }
+                };
+
+                if heights[stack] != level {
+                    return Err(error(
+                        span.start + 1,
+                        DrawingErrorKind::FloatingCrate(label),
+                    ));
+                }
+
+                filled[stack] = true;
+                heights[stack] += 1;
+                place(stack, label);
+            }
+
+            Ok(heights)
+        },
+    )
+}
+
+fn parse_footer(line: usize, footer: &str) -> Result<Vec<Span>, DrawingError> {
+    let mut columns = Vec::new();
+
+    for (start, number) in tokens(footer) {
+        let expected = columns.len() + 1;
+        if number.parse() != Ok(expected) {
+            let kind = if number.starts_with('[') {
+                DrawingErrorKind::MissingFooter
+            } else {
+                DrawingErrorKind::UnexpectedStackNumber {
+                    expected,
+                    found: number.clone(),
+                }
+            };
+            return Err(DrawingError {
+                line,
+                column: start + 1,
+                kind,
+            });
+        }
+
+        columns.push(Span {
+            start,
+            end: start + number.chars().count() - 1,
+        });
+    }
+
+    Ok(columns)
+}
+
+fn parse_row(line: usize, row: &str) -> Result<Vec<(Span, String)>, DrawingError> {
+    let mut crates = Vec::new();
+    let mut chars = row.chars().enumerate();
+
+    while let Some((start, c)) = chars.next() {
+        let error = |column, kind| DrawingError { line, column, kind };
+
+        match c {
+            ' ' => continue,
+            '[' => {}
+            c => return Err(error(start + 1, DrawingErrorKind::UnexpectedCharacter(c))),
+        }
+
+        let mut label = String::new();
+        let end = loop {
+            match chars.next() {
+                Some((end, ']')) => break end,
+                Some((column, c)) if c == '[' || c.is_whitespace() => {
+                    return Err(error(column + 1, DrawingErrorKind::UnexpectedCharacter(c)))
+                }
+                Some((_, c)) => label.push(c),
+                None => return Err(error(start + 1, DrawingErrorKind::UnterminatedCrate)),
+            }
+        };
+
+        if label.is_empty() {
+            return Err(error(start + 1, DrawingErrorKind::EmptyCrate));
+        }
+
+        crates.push((Span { start, end }, label));
+    }
+
+    Ok(crates)
+}
+
+fn tokens(line: &str) -> Vec<(usize, String)> {
+    let mut tokens: Vec<(usize, String)> = Vec::new();
+    let mut previous = ' ';
+
+    for (column, c) in line.chars().enumerate() {
+        if !c.is_whitespace() {
+            if previous.is_whitespace() {
+                tokens.push((column, String::new()));
+            }
+            if let Some((_, token)) = tokens.last_mut() {
+                token.push(c);
+            }
+        }
+        previous = c;
+    }
+
+    tokens
+}
+
+pub(crate) fn render(stacks: &Stacks) -> String {
+    let widths = stacks
+        .iter()
+        .enumerate()
+        .map(|(index, stack)| {
+            stack
+                .iter()
+                .map(|label| label.chars().count() + 2)
+                .chain([(index + 1).to_string().len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let height = stacks.iter().map(Vec::len).max().unwrap_or_default();
+    let centred = |text: &str, width: usize| {
+        let padding = width - text.chars().count();
+        let left = padding.div_ceil(2);
+        format!("{}{text}{}", " ".repeat(left), " ".repeat(padding - left))
+    };
+
+    let mut drawing = String::new();
+    for level in (0..height).rev() {
+        let row = stacks
+            .iter()
+            .zip(&widths)
+            .map(|(stack, &width)| match stack.get(level) {
+                Some(label) => centred(&format!("[{label}]"), width),
+                None => " ".repeat(width),
+            })
+            .collect::<Vec<_>>();
+        drawing.push_str(&row.join(" "));
+        drawing.push('\n');
+    }
+
+    let footer = widths
+        .iter()
+        .enumerate()
+        .map(|(index, &width)| centred(&(index + 1).to_string(), width))
+        .collect::<Vec<_>>();
+    drawing.push_str(&footer.join(" "));
+    drawing.push('\n');
+
+    drawing
+}
+
+impl fmt::Display for DrawingErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawingErrorKind::MissingFooter => write!(f, "missing numbered footer"),
+            DrawingErrorKind::UnexpectedStackNumber { expected, found } => {
+                write!(f, "expected stack number {expected}, found {found:?}")
+            }
+            DrawingErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {c:?}"),
+            DrawingErrorKind::UnterminatedCrate => write!(f, "crate is missing its closing `]`"),
+            DrawingErrorKind::EmptyCrate => write!(f, "crate has no label"),
+            DrawingErrorKind::UnalignedCrate(label) => {
+                write!(f, "crate [{label}] does not line up with exactly one stack")
+            }
+            DrawingErrorKind::FloatingCrate(label) => {
+                write!(f, "crate [{label}] is floating above an empty space")
+            }
+        }
+    }
+}
+
+impl fmt::Display for DrawingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_the_example_drawing() {
+        let drawing = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\nmove 1 from 2 to 1\n";
+
+        assert_eq!(
+            parse_start_stacks(drawing),
+            Ok(vec![
+                vec!["Z".to_owned(), "N".to_owned()],
+                vec!["M".to_owned(), "C".to_owned(), "D".to_owned()],
+                vec!["P".to_owned()],
+            ])
+        );
+        assert_eq!(stack_heights(drawing), Ok(vec![2, 3, 1]));
+        assert_eq!(crate_at(drawing, 1, 2), Some("D".to_owned()));
+        assert_eq!(crate_at(drawing, 0, 0), Some("Z".to_owned()));
+        assert_eq!(crate_at(drawing, 2, 1), None);
+    }
+
+    #[test]
+    fn renders_the_example_drawing() {
+        let drawing = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+        assert_eq!(render(&parse_start_stacks(drawing).unwrap()), drawing);
+    }
+
+    #[test]
+    fn rendering_round_trips() {
+        let stacks = vec![
+            vec!["CD".to_owned(), "AB".to_owned()],
+            vec![],
+            vec!["E".to_owned(), "LONG".to_owned(), "F".to_owned()],
+            vec!["G".to_owned()],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec!["H".to_owned()],
+            vec!["I".to_owned(), "J".to_owned()],
+        ];
+        assert_eq!(parse_start_stacks(&render(&stacks)), Ok(stacks));
+    }
+
+    #[test]
+    fn parses_wide_labels_and_many_stacks() {
+        let drawing = "\
+[AB]                                             [LM]
+[CD] [E]  [F]  [G]  [H]  [I]  [J]  [K]  [X]  [Y] [NO]
+ 1    2    3    4    5    6    7    8    9   10   11
+";
+
+        let stacks = parse_start_stacks(drawing).unwrap();
+        assert_eq!(stacks.len(), 11);
+        assert_eq!(stacks[0], vec!["CD".to_owned(), "AB".to_owned()]);
+        assert_eq!(stacks[9], vec!["Y".to_owned()]);
+        assert_eq!(stacks[10], vec!["NO".to_owned(), "LM".to_owned()]);
+    }
+
+    #[test]
+    fn malformed_drawings_report_their_position() {
+        let error = |drawing: &str| {
+            let error = parse_start_stacks(drawing).unwrap_err();
+            (error.line, error.column, error.kind)
+        };
+
+        assert_eq!(error("[A]\n[B]\n"), (2, 1, DrawingErrorKind::MissingFooter));
+        assert_eq!(
+            error("[A] [B]\n 1   3\n"),
+            (
+                2,
+                6,
+                DrawingErrorKind::UnexpectedStackNumber {
+                    expected: 2,
+                    found: "3".to_owned()
+                }
+            )
+        );
+        assert_eq!(
+            error("[A] [B\n 1   2\n"),
+            (1, 5, DrawingErrorKind::UnterminatedCrate)
+        );
+        assert_eq!(
+            error("  [A]\n 1   2\n"),
+            (1, 3, DrawingErrorKind::UnalignedCrate("A".to_owned()))
+        );
+        assert_eq!(
+            error("[A]\n    [B]\n 1   2\n"),
+            (1, 1, DrawingErrorKind::FloatingCrate("A".to_owned()))
+        );
+        assert_eq!(
+            error("[A] x\n 1   2\n"),
+            (1, 5, DrawingErrorKind::UnexpectedCharacter('x'))
+        );
+    }
+}
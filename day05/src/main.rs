@@ -1,104 +1,615 @@
-fn parse_start_stacks(input: &str) -> Stacks {
-    input
-        .lines()
-        .take_while(|line| !line.is_empty())
-        .collect::<Vec<_>>()
-        .into_iter()
-        .rev()
+use backtrack::top_crates_by_backtracking;
+use cranes::{
+    parse_crane, parse_limited_grab, unapply_operations, CrateMover9000, CrateMover9001, Inverting,
+    MoveCrates, Wearing,
+};
+use drawing::{parse_start_stacks, render, DrawingError};
+use planner::{plan, Cost, CratesLifted, OperationCount};
+use rope::RopeStacks;
+use std::{
+    env, fmt, fs,
+    io::{self, Write},
+    process,
+};
+
+mod backtrack;
+mod cranes;
+mod drawing;
+mod planner;
+mod rope;
+mod synthetic;
+mod trace;
+
+fn parse_operations(input: &str) -> impl Iterator<Item = Result<Operation, OperationError>> + '_ {
+    (1..)
+        .zip(input.lines())
+        .skip_while(|(_, line)| !line.is_empty())
         .skip(1)
-        .fold(Stacks::default(), |mut stacks, line| {
-            for (stack, cr8) in line
-                .chars()
-                .skip(1)
-                .step_by(4)
-                .enumerate()
-                .filter(|(_, cr8)| *cr8 != ' ')
-            {
-                match stacks.get_mut(stack) {
-                    Some(stack) => stack.push(cr8),
-                    None => stacks.push(vec![cr8]),
-                }
-            }
-            stacks
-        })
+        .map(|(line, text)| parse_operation(line, text))
 }
 
-fn parse_operations(input: &str) -> impl Iterator<Item = Operation> + '_ {
-    input
-        .lines()
-        .skip_while(|line| !line.is_empty())
-        .skip(1)
-        .map(|line| {
-            let mut split = line.split_whitespace();
-            let mut get_next =
-                || -> usize { split.nth(1).map(|count| count.parse().unwrap()).unwrap() };
-            Operation {
-                count: get_next(),
-                from: get_next() - 1,
-                to: get_next() - 1,
-            }
-        })
+fn parse_operation(line: usize, text: &str) -> Result<Operation, OperationError> {
+    let error = || OperationError {
+        line,
+        text: text.to_owned(),
+    };
+    let mut split = text.split_whitespace();
+    let mut get_next = |keyword| -> Result<usize, OperationError> {
+        match (split.next(), split.next()) {
+            (Some(word), Some(number)) if word == keyword => number.parse().map_err(|_| error()),
+            _ => Err(error()),
+        }
+    };
+    let operation = Operation {
+        line,
+        count: get_next("move")?,
+        from: get_next("from")?.checked_sub(1).ok_or_else(error)?,
+        to: get_next("to")?.checked_sub(1).ok_or_else(error)?,
+    };
+    match split.next() {
+        Some(_) => Err(error()),
+        None => Ok(operation),
+    }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 struct Operation {
+    line: usize,
     count: usize,
     from: usize,
     to: usize,
 }
 
-type Stacks = Vec<Vec<char>>;
+type Crate = String;
 
-trait MoveCrates {
-    fn move_crates(stacks: Stacks, operation: Operation) -> Stacks;
+type Stacks = Vec<Vec<Crate>>;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct OperationError {
+    line: usize,
+    text: String,
 }
 
-struct CrateMover9000;
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum MoveErrorKind {
+    NoSuchStack {
+        stack: usize,
+        stacks: usize,
+    },
+    NotEnoughCrates {
+        stack: usize,
+        requested: usize,
+        available: usize,
+    },
+    WornOut {
+        lifts: usize,
+    },
+}
 
-impl MoveCrates for CrateMover9000 {
-    fn move_crates(mut stacks: Vec<Vec<char>>, operation: Operation) -> Stacks {
-        let crates_on_source_stack = stacks[operation.from].len();
-        let crates = stacks[operation.from]
-            .drain(crates_on_source_stack - operation.count..)
-            .rev()
-            .collect::<Vec<_>>();
-        stacks[operation.to].extend(crates);
-        stacks
-    }
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct MoveError {
+    line: usize,
+    kind: MoveErrorKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Error {
+    Drawing(DrawingError),
+    Operation(OperationError),
+    Move(MoveError),
+}
+
+fn rearrange<C>(
+    input: &str,
+    crane: &mut C,
+    mut step: impl FnMut(&Operation, &Stacks),
+) -> Result<Stacks, Error>
+where
+    C: MoveCrates + ?Sized,
+{
+    parse_operations(input).try_fold(parse_start_stacks(input)?, |stacks, operation| {
+        let operation = operation?;
+        let stacks = crane.move_crates(stacks, operation)?;
+        step(&operation, &stacks);
+        Ok(stacks)
+    })
+}
+
+fn get_top_crates_following_rearrangement<C>(input: &str, crane: &mut C) -> Result<String, Error>
+where
+    C: MoveCrates + ?Sized,
+{
+    Ok(top_crates(rearrange(input, crane, |_, _| {})?))
+}
+
+fn get_top_crates_with_trace<C, W>(
+    input: &str,
+    crane: &mut C,
+    out: &mut W,
+) -> io::Result<Result<String, Error>>
+where
+    C: MoveCrates + ?Sized,
+    W: Write,
+{
+    let mut written = Ok(());
+    let stacks = rearrange(input, crane, |operation, stacks| {
+        if written.is_ok() {
+            written = trace::write_record(out, operation, stacks);
+        }
+    });
+    written?;
+    Ok(stacks.map(top_crates))
 }
 
-struct CrateMover9001;
+// Empty stacks keep their place in the answer.
+const NO_CRATE: &str = " ";
 
-impl MoveCrates for CrateMover9001 {
-    fn move_crates(mut stacks: Vec<Vec<char>>, operation: Operation) -> Stacks {
-        let crates_on_source_stack = stacks[operation.from].len();
-        let crates = stacks[operation.from]
-            .drain(crates_on_source_stack - operation.count..)
-            .collect::<Vec<_>>();
-        stacks[operation.to].extend(crates);
-        stacks
+fn top_crates(stacks: Stacks) -> String {
+    stacks.into_iter().fold(String::new(), |mut string, stack| {
+        string.push_str(stack.last().map_or(NO_CRATE, String::as_str));
+        string
+    })
+}
+
+fn get_top_crates_with_rope(input: &str, preserve_order: bool) -> Result<String, Error> {
+    let mut stacks = RopeStacks::from(parse_start_stacks(input)?);
+    for operation in parse_operations(input) {
+        stacks.move_crates(operation?, preserve_order)?;
     }
+    Ok(stacks.top_crates())
 }
 
-fn get_top_crates_following_rearrangement<C>(input: &str) -> String
+fn print_traced_top_crates<C>(
+    input: &str,
+    crane: &mut C,
+    path: &str,
+) -> io::Result<Result<(), Error>>
 where
-    C: MoveCrates,
+    C: MoveCrates + ?Sized,
 {
-    parse_operations(input)
-        .fold(parse_start_stacks(input), C::move_crates)
-        .into_iter()
-        .fold(String::new(), |mut string, stack| {
-            string.push(stack.last().copied().unwrap());
-            string
-        })
+    let mut trace = io::BufWriter::new(fs::File::create(path)?);
+    let top_crates = get_top_crates_with_trace(input, crane, &mut trace)?;
+    trace.flush()?;
+    Ok(top_crates.map(|top_crates| println!("Top crates: {top_crates}")))
+}
+
+fn print_frames<C>(input: &str, crane: &mut C) -> Result<(), Error>
+where
+    C: MoveCrates + ?Sized,
+{
+    print!("{}", render(&parse_start_stacks(input)?));
+    rearrange(input, crane, |operation, stacks| {
+        print!("\n{operation}\n{}", render(stacks));
+    })
+    .map(|_| ())
+}
+
+fn print_recovered_start(input: &str, path: &str, model: &str) -> Result<(), String> {
+    let operations = parse_operations(input)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| Error::from(error).to_string())?;
+
+    let mut crane = parse_crane(model).ok_or_else(|| format!("unknown crane model {model:?}"))?;
+
+    let drawing = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    let stacks = parse_start_stacks(&drawing).map_err(|error| format!("{path}:{error}"))?;
+
+    let start = unapply_operations(stacks, &operations, &mut crane)
+        .map_err(|error| format!("log is inconsistent with the final state: {error}"))?;
+    print!("{}", render(&start));
+    Ok(())
+}
+
+fn print_plan(input: &str, path: &str, model: &str, cost: &str) -> Result<(), String> {
+    let cost: &dyn Cost = match cost {
+        "operations" => &OperationCount,
+        "lifted" => &CratesLifted,
+        _ => {
+            return Err(format!(
+                "unknown cost {cost:?}, expected operations or lifted"
+            ))
+        }
+    };
+
+    let start = parse_start_stacks(input).map_err(|error| error.to_string())?;
+    let drawing = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    let target = parse_start_stacks(&drawing).map_err(|error| format!("{path}:{error}"))?;
+
+    const LIMIT: usize = 1_000_000;
+    let operations = match model {
+        "9000" => plan(&start, &target, &CrateMover9000, cost, LIMIT),
+        "9001" => plan(&start, &target, &CrateMover9001, cost, LIMIT),
+        "inverting" => plan(&start, &target, &Inverting, cost, LIMIT),
+        _ => match parse_limited_grab(model) {
+            Some(crane) => plan(&start, &target, &crane, cost, LIMIT),
+            None => return Err(format!("unknown crane model {model:?}")),
+        },
+    };
+
+    for operation in operations.map_err(|error| error.to_string())? {
+        println!("{operation}");
+    }
+    Ok(())
+}
+
+impl From<DrawingError> for Error {
+    fn from(error: DrawingError) -> Self {
+        Error::Drawing(error)
+    }
+}
+
+impl From<OperationError> for Error {
+    fn from(error: OperationError) -> Self {
+        Error::Operation(error)
+    }
+}
+
+impl From<MoveError> for Error {
+    fn from(error: MoveError) -> Self {
+        Error::Move(error)
+    }
+}
+
+impl Operation {
+    fn reversed(self) -> Self {
+        Operation {
+            from: self.to,
+            to: self.from,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.count,
+            self.from + 1,
+            self.to + 1
+        )
+    }
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            MoveErrorKind::NoSuchStack { stack, stacks } => write!(
+                f,
+                "{}: there is no stack {stack}, only {stacks} stacks",
+                self.line
+            ),
+            MoveErrorKind::NotEnoughCrates {
+                stack,
+                requested,
+                available,
+            } => write!(
+                f,
+                "{}: cannot move {requested} crates from stack {stack}, it only has {available}",
+                self.line
+            ),
+            MoveErrorKind::WornOut { lifts } => {
+                write!(f, "{}: crane wore out after {lifts} lifts", self.line)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Drawing(error) => write!(f, "{error}"),
+            Error::Operation(error) => write!(
+                f,
+                "{}: expected `move N from A to B`, found {:?}",
+                error.line, error.text
+            ),
+            Error::Move(error) => write!(f, "{error}"),
+        }
+    }
 }
 
 fn main() {
-    println!(
-        "Part 1: {}",
-        get_top_crates_following_rearrangement::<CrateMover9000>(include_str!("input.txt"))
-    );
-    println!(
-        "Part 2: {}",
-        get_top_crates_following_rearrangement::<CrateMover9001>(include_str!("input.txt"))
-    );
+    let option = |name| env::args().skip_while(|arg| arg != name).nth(1);
+
+    if let Some(path) = option("--recover") {
+        let model = option("--crane").unwrap_or_else(|| "9001".to_owned());
+        if let Err(error) = print_recovered_start(include_str!("input.txt"), &path, &model) {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(operations) = option("--generate").or_else(|| option("--bench")) {
+        let number = |name, default| {
+            option(name)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        let Ok(operations) = operations.parse() else {
+            eprintln!("expected a number of operations, found {operations:?}");
+            process::exit(1);
+        };
+        let procedure = synthetic::generate(
+            number("--stacks", 9),
+            number("--height", 1000),
+            operations,
+            number("--max-count", 1000),
+            number("--seed", 1) as u64,
+        );
+
+        if option("--generate").is_some() {
+            print!("{procedure}");
+        } else if let Err(error) = synthetic::benchmark(&procedure) {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(path) = option("--plan") {
+        let model = option("--crane").unwrap_or_else(|| "9001".to_owned());
+        let cost = option("--cost").unwrap_or_else(|| "operations".to_owned());
+        if let Err(error) = print_plan(include_str!("input.txt"), &path, &model, &cost) {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    let step = option("--step");
+    if let Some(model) = step
+        .clone()
+        .or_else(|| option("--crane"))
+        .or_else(|| option("--trace").map(|_| "9001".to_owned()))
+    {
+        let Some(mut crane) = parse_crane(&model) else {
+            eprintln!("unknown crane model {model:?}, expected 9000, 9001, inverting or grab:K");
+            process::exit(1);
+        };
+        if let Some(limit) = option("--wear").and_then(|limit| limit.parse().ok()) {
+            crane = Box::new(Wearing::new(crane, limit));
+        }
+
+        let result = if step.is_some() {
+            print_frames(include_str!("input.txt"), &mut crane)
+        } else if let Some(path) = option("--trace") {
+            match print_traced_top_crates(include_str!("input.txt"), &mut crane, &path) {
+                Ok(result) => result,
+                Err(error) => {
+                    eprintln!("{path}: {error}");
+                    process::exit(1);
+                }
+            }
+        } else {
+            get_top_crates_following_rearrangement(include_str!("input.txt"), &mut crane)
+                .map(|top_crates| println!("Top crates: {top_crates}"))
+        };
+        if let Err(error) = result {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if env::args().any(|arg| arg == "--backtrack") {
+        let mut failed = false;
+        for (part, preserve_order) in [(1, false), (2, true)] {
+            match top_crates_by_backtracking(include_str!("input.txt"), preserve_order) {
+                Ok(top_crates) => println!("Part {part}: {top_crates}"),
+                Err(error) => {
+                    eprintln!("Part {part}: {error}");
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if env::args().any(|arg| arg == "--rope") {
+        let mut failed = false;
+        for (part, preserve_order) in [(1, false), (2, true)] {
+            match get_top_crates_with_rope(include_str!("input.txt"), preserve_order) {
+                Ok(top_crates) => println!("Part {part}: {top_crates}"),
+                Err(error) => {
+                    eprintln!("Part {part}: {error}");
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut failed = false;
+    match get_top_crates_following_rearrangement(include_str!("input.txt"), &mut CrateMover9000) {
+        Ok(top_crates) => println!("Part 1: {top_crates}"),
+        Err(error) => {
+            eprintln!("Part 1: {error}");
+            failed = true;
+        }
+    }
+    match get_top_crates_following_rearrangement(include_str!("input.txt"), &mut CrateMover9001) {
+        Ok(top_crates) => println!("Part 2: {top_crates}"),
+        Err(error) => {
+            eprintln!("Part 2: {error}");
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2
+";
+
+    #[test]
+    fn example_rearrangements() {
+        assert_eq!(
+            get_top_crates_following_rearrangement(EXAMPLE, &mut CrateMover9000),
+            Ok("CMZ".to_owned())
+        );
+        assert_eq!(
+            get_top_crates_following_rearrangement(EXAMPLE, &mut CrateMover9001),
+            Ok("MCD".to_owned())
+        );
+        assert_eq!(
+            get_top_crates_with_rope(EXAMPLE, false),
+            Ok("CMZ".to_owned())
+        );
+        assert_eq!(
+            get_top_crates_with_rope(EXAMPLE, true),
+            Ok("MCD".to_owned())
+        );
+    }
+
+    #[test]
+    fn configurable_cranes() {
+        let top_crates = |model| {
+            get_top_crates_following_rearrangement(EXAMPLE, &mut parse_crane(model).unwrap())
+        };
+
+        assert_eq!(top_crates("grab:1"), Ok("CMZ".to_owned()));
+        assert_eq!(top_crates("grab:2"), Ok("MCZ".to_owned()));
+        assert_eq!(top_crates("grab:3"), Ok("MCD".to_owned()));
+        assert_eq!(top_crates("inverting"), Ok("CMN".to_owned()));
+
+        let lift = Operation {
+            line: 1,
+            count: 5,
+            from: 0,
+            to: 1,
+        };
+        let stacks = vec![
+            ["A", "B", "C", "D", "E"].map(str::to_owned).to_vec(),
+            vec![],
+        ];
+        assert_eq!(
+            Inverting.move_crates(stacks, lift),
+            Ok(vec![
+                vec![],
+                ["A", "C", "B", "E", "D"].map(str::to_owned).to_vec()
+            ])
+        );
+
+        assert_eq!(
+            get_top_crates_following_rearrangement(EXAMPLE, &mut Wearing::new(CrateMover9001, 2)),
+            Err(Error::Move(MoveError {
+                line: 8,
+                kind: MoveErrorKind::WornOut { lifts: 2 },
+            }))
+        );
+    }
+
+    #[test]
+    fn rearrangements_can_be_reversed() {
+        let start = parse_start_stacks(EXAMPLE).unwrap();
+        let operations = parse_operations(EXAMPLE)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        for model in ["9000", "9001", "grab:1", "grab:2", "inverting"] {
+            let mut crane = parse_crane(model).unwrap();
+            let end = rearrange(EXAMPLE, &mut crane, |_, _| {}).unwrap();
+            assert_eq!(
+                unapply_operations(end, &operations, &mut crane),
+                Ok(start.clone()),
+                "{model}"
+            );
+        }
+    }
+
+    #[test]
+    fn inconsistent_logs_cannot_be_reversed() {
+        let end = parse_start_stacks("[A]        \n[B] [C] [D]\n 1   2   3 \n").unwrap();
+        let operations = parse_operations(EXAMPLE)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            unapply_operations(end, &operations, &mut CrateMover9001),
+            Err(MoveError {
+                line: 7,
+                kind: MoveErrorKind::NotEnoughCrates {
+                    stack: 3,
+                    requested: 3,
+                    available: 1,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn bad_operations_are_reported() {
+        let input = EXAMPLE.replace("move 3 from 1 to 3", "move 4 from 1 to 3");
+        assert_eq!(
+            get_top_crates_following_rearrangement(&input, &mut CrateMover9001),
+            Err(Error::Move(MoveError {
+                line: 7,
+                kind: MoveErrorKind::NotEnoughCrates {
+                    stack: 1,
+                    requested: 4,
+                    available: 3,
+                },
+            }))
+        );
+
+        let input = EXAMPLE.replace("move 1 from 1 to 2", "move 1 from 1 to 4");
+        assert_eq!(
+            get_top_crates_following_rearrangement(&input, &mut CrateMover9000),
+            Err(Error::Move(MoveError {
+                line: 9,
+                kind: MoveErrorKind::NoSuchStack {
+                    stack: 4,
+                    stacks: 3
+                },
+            }))
+        );
+
+        let input = EXAMPLE.replace("move 2 from 2 to 1", "move 2 from 0 to 1");
+        assert_eq!(
+            get_top_crates_following_rearrangement(&input, &mut CrateMover9000),
+            Err(Error::Operation(OperationError {
+                line: 8,
+                text: "move 2 from 0 to 1".to_owned(),
+            }))
+        );
+    }
+
+    #[test]
+    fn empty_stacks_have_no_top_crate() {
+        let input = EXAMPLE.replace(
+            "move 1 from 1 to 2\n",
+            "move 1 from 1 to 2\nmove 1 from 2 to 1\nmove 2 from 1 to 2\n",
+        );
+        assert_eq!(
+            get_top_crates_following_rearrangement(&input, &mut CrateMover9001),
+            Ok(" CD".to_owned())
+        );
+        assert_eq!(get_top_crates_with_rope(&input, true), Ok(" CD".to_owned()));
+        assert_eq!(
+            top_crates_by_backtracking(&input, true),
+            Ok(" CD".to_owned())
+        );
+    }
 }
@@ -0,0 +1,295 @@
+use crate::{cranes::MoveCrates, Operation, Stacks};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    hash::Hash,
+};
+
+pub(crate) trait Cost {
+    fn cost(&self, operation: &Operation) -> usize;
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct OperationCount;
+
+impl Cost for OperationCount {
+    fn cost(&self, _: &Operation) -> usize {
+        1
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct CratesLifted;
+
+impl Cost for CratesLifted {
+    fn cost(&self, operation: &Operation) -> usize {
+        operation.count
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum NoPlan {
+    DifferentStackCount { start: usize, target: usize },
+    DifferentCrates,
+    Unreachable { explored: usize },
+    LimitReached { explored: usize },
+}
+
+// Each state pairs the stacks with the crane as it is after reaching them, so
+// cranes that change as they work (wearing) are planned for too.
+pub(crate) fn plan<C, K>(
+    start: &Stacks,
+    target: &Stacks,
+    crane: &C,
+    cost: &K,
+    limit: usize,
+) -> Result<Vec<Operation>, NoPlan>
+where
+    C: MoveCrates + Clone + Eq + Hash,
+    K: Cost + ?Sized,
+{
+    if start.len() != target.len() {
+        return Err(NoPlan::DifferentStackCount {
+            start: start.len(),
+            target: target.len(),
+        });
+    }
+
+    let sorted = |stacks: &Stacks| {
+        let mut crates = stacks.concat();
+        crates.sort();
+        crates
+    };
+    if sorted(start) != sorted(target) {
+        return Err(NoPlan::DifferentCrates);
+    }
+
+    let mut states = vec![(start.clone(), crane.clone())];
+    let mut ids = HashMap::from([(states[0].clone(), 0)]);
+    let mut best = vec![0];
+    let mut previous: Vec<Option<(usize, Operation)>> = vec![None];
+    let mut queue = BinaryHeap::from([Reverse((0, 0))]);
+
+    while let Some(Reverse((distance, id))) = queue.pop() {
+        if distance > best[id] {
+            continue;
+        }
+
+        if states[id].0 == *target {
+            let mut operations = Vec::new();
+            let mut id = id;
+            while let Some((parent, operation)) = previous[id] {
+                operations.push(operation);
+                id = parent;
+            }
+            operations.reverse();
+            for (line, operation) in (1..).zip(&mut operations) {
+                operation.line = line;
+            }
+            return Ok(operations);
+        }
+
+        if states.len() > limit {
+            return Err(NoPlan::LimitReached {
+                explored: states.len(),
+            });
+        }
+
+        let heights = states[id].0.iter().map(Vec::len).collect();
+        for operation in operations(heights) {
+            let (stacks, crane) = &states[id];
+            let mut crane = crane.clone();
+            let Ok(stacks) = crane.move_crates(stacks.clone(), operation) else {
+                continue;
+            };
+            let next = (stacks, crane);
+            let distance = distance + cost.cost(&operation);
+
+            let next_id = *ids.entry(next).or_insert_with_key(|next| {
+                states.push(next.clone());
+                best.push(usize::MAX);
+                previous.push(None);
+                states.len() - 1
+            });
+
+            if distance < best[next_id] {
+                best[next_id] = distance;
+                previous[next_id] = Some((id, operation));
+                queue.push(Reverse((distance, next_id)));
+            }
+        }
+    }
+
+    Err(NoPlan::Unreachable {
+        explored: states.len(),
+    })
+}
+
+fn operations(heights: Vec<usize>) -> impl Iterator<Item = Operation> {
+    let stacks = heights.len();
+    (0..stacks).flat_map(move |from| {
+        let height = heights[from];
+        (0..stacks)
+            .filter(move |&to| to != from)
+            .flat_map(move |to| {
+                (1..=height).map(move |count| Operation {
+                    line: 0,
+                    count,
+                    from,
+                    to,
+                })
+            })
+    })
+}
+
+impl fmt::Display for NoPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoPlan::DifferentStackCount { start, target } => write!(
+                f,
+                "no plan exists: start has {start} stacks but target has {target}"
+            ),
+            NoPlan::DifferentCrates => {
+                write!(f, "no plan exists: start and target hold different crates")
+            }
+            NoPlan::Unreachable { explored } => write!(
+                f,
+                "no plan exists: target unreachable from all {explored} reachable states"
+            ),
+            NoPlan::LimitReached { explored } => {
+                write!(f, "gave up after exploring {explored} states")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cranes::{CrateMover9000, CrateMover9001, Inverting, LimitedGrab, Wearing},
+        drawing::parse_start_stacks,
+    };
+
+    const START: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+
+    fn apply<C: MoveCrates>(crane: &mut C, start: &Stacks, operations: &[Operation]) -> Stacks {
+        operations.iter().fold(start.clone(), |stacks, &operation| {
+            crane.move_crates(stacks, operation).unwrap()
+        })
+    }
+
+    #[test]
+    fn plans_reach_the_target() {
+        let start = parse_start_stacks(START).unwrap();
+        let target =
+            parse_start_stacks("        [Z]\n        [N]\n[M] [C] [D]\n 1   2   3 \n").unwrap();
+        let target = vec![target[0].clone(), target[1].clone(), {
+            let mut stack = vec!["P".to_owned()];
+            stack.extend(target[2].iter().cloned());
+            stack
+        }];
+
+        fn check<C: MoveCrates + Clone + Eq + Hash>(crane: C, start: &Stacks, target: &Stacks) {
+            let operations = plan(start, target, &crane, &OperationCount, 100_000).unwrap();
+            assert_eq!(apply(&mut crane.clone(), start, &operations), *target);
+            assert_eq!(
+                operations
+                    .iter()
+                    .map(|operation| operation.line)
+                    .collect::<Vec<_>>(),
+                (1..=operations.len()).collect::<Vec<_>>()
+            );
+        }
+
+        check(CrateMover9000, &start, &target);
+        check(CrateMover9001, &start, &target);
+        check(LimitedGrab::new(2), &start, &target);
+        check(Inverting, &start, &target);
+        check(Wearing::new(CrateMover9001, 5), &start, &target);
+    }
+
+    #[test]
+    fn plans_are_shortest_under_their_cost() {
+        let start = parse_start_stacks(START).unwrap();
+        let target =
+            parse_start_stacks("[D]        \n[C]        \n[M] [N]    \n[P] [Z]    \n 1   2   3 \n")
+                .unwrap();
+
+        let operations = plan(&start, &target, &CrateMover9001, &OperationCount, 100_000).unwrap();
+        assert_eq!(operations.len(), 3);
+        let lifted = plan(&start, &target, &CrateMover9001, &CratesLifted, 100_000).unwrap();
+        assert!(
+            lifted
+                .iter()
+                .map(|operation| operation.count)
+                .sum::<usize>()
+                <= operations
+                    .iter()
+                    .map(|operation| operation.count)
+                    .sum::<usize>()
+        );
+
+        let operations = plan(&start, &start, &CrateMover9000, &OperationCount, 100_000);
+        assert_eq!(operations, Ok(vec![]));
+    }
+
+    #[test]
+    fn worn_cranes_run_out_of_moves() {
+        let start = parse_start_stacks(START).unwrap();
+        let target =
+            parse_start_stacks("[D]        \n[C]        \n[M] [N]    \n[P] [Z]    \n 1   2   3 \n")
+                .unwrap();
+
+        let crane = Wearing::new(CrateMover9001, 3);
+        assert_eq!(
+            plan(&start, &target, &crane, &OperationCount, 100_000).map(|plan| plan.len()),
+            Ok(3)
+        );
+        let crane = Wearing::new(CrateMover9001, 2);
+        assert!(matches!(
+            plan(&start, &target, &crane, &OperationCount, 100_000),
+            Err(NoPlan::Unreachable { .. })
+        ));
+    }
+
+    #[test]
+    fn impossible_targets_are_reported() {
+        let start = parse_start_stacks(START).unwrap();
+
+        assert_eq!(
+            plan(
+                &start,
+                &start[..2].to_vec(),
+                &CrateMover9000,
+                &OperationCount,
+                100
+            ),
+            Err(NoPlan::DifferentStackCount {
+                start: 3,
+                target: 2
+            })
+        );
+
+        let mut target = start.clone();
+        target[2][0] = "Q".to_owned();
+        assert_eq!(
+            plan(&start, &target, &CrateMover9000, &OperationCount, 100),
+            Err(NoPlan::DifferentCrates)
+        );
+
+        let single = parse_start_stacks("[A]\n[B]\n 1 \n").unwrap();
+        let flipped = parse_start_stacks("[B]\n[A]\n 1 \n").unwrap();
+        assert_eq!(
+            plan(&single, &flipped, &CrateMover9001, &OperationCount, 100),
+            Err(NoPlan::Unreachable { explored: 1 })
+        );
+
+        let target = vec![vec![], vec![], start.concat()];
+        assert!(matches!(
+            plan(&start, &target, &CrateMover9000, &OperationCount, 10),
+            Err(NoPlan::LimitReached { .. })
+        ));
+    }
+}
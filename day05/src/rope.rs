@@ -0,0 +1,247 @@
+use crate::{cranes::check, Crate, MoveError, Operation, Stacks, NO_CRATE};
+
+// Each stack is an implicit treap over the crates, bottom to top, so moving
+// any number of crates is a split and a merge; reversing the lifted crates for
+// the CrateMover 9000 is a flag that is only pushed down when a split needs it.
+#[derive(Debug, Clone)]
+pub(crate) struct RopeStacks {
+    crates: Vec<Crate>,
+    nodes: Vec<Node>,
+    stacks: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    priority: u64,
+    size: usize,
+    reversed: bool,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl RopeStacks {
+    pub(crate) fn move_crates(
+        &mut self,
+        operation: Operation,
+        preserve_order: bool,
+    ) -> Result<(), MoveError> {
+        let remaining = check(operation, self.stacks.len(), |stack| {
+            self.size(self.stacks[stack])
+        })?;
+
+        let (source, lifted) = self.split(self.stacks[operation.from], remaining);
+        if let (false, Some(lifted)) = (preserve_order, lifted) {
+            self.nodes[lifted].reversed ^= true;
+        }
+        self.stacks[operation.from] = source;
+        self.stacks[operation.to] = self.merge(self.stacks[operation.to], lifted);
+        Ok(())
+    }
+
+    pub(crate) fn top_crates(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|&root| {
+                let (mut node, mut reversed) = (root?, false);
+                loop {
+                    reversed ^= self.nodes[node].reversed;
+                    let next = if reversed {
+                        self.nodes[node].left
+                    } else {
+                        self.nodes[node].right
+                    };
+                    match next {
+                        Some(next) => node = next,
+                        None => return Some(&self.crates[node]),
+                    }
+                }
+            })
+            .fold(String::new(), |mut string, top: Option<&Crate>| {
+                string.push_str(top.map_or(NO_CRATE, String::as_str));
+                string
+            })
+    }
+
+    pub(crate) fn to_stacks(&self) -> Stacks {
+        self.stacks
+            .iter()
+            .map(|&root| {
+                let mut stack = Vec::new();
+                self.collect(root, false, &mut stack);
+                stack
+            })
+            .collect()
+    }
+
+    fn collect(&self, node: Option<usize>, reversed: bool, stack: &mut Vec<Crate>) {
+        let Some(node) = node else {
+            return;
+        };
+        let reversed = reversed ^ self.nodes[node].reversed;
+        let (first, last) = if reversed {
+            (self.nodes[node].right, self.nodes[node].left)
+        } else {
+            (self.nodes[node].left, self.nodes[node].right)
+        };
+        self.collect(first, reversed, stack);
+        stack.push(self.crates[node].clone());
+        self.collect(last, reversed, stack);
+    }
+
+    fn size(&self, node: Option<usize>) -> usize {
+        node.map_or(0, |node| self.nodes[node].size)
+    }
+
+    fn update(&mut self, node: usize) {
+        let Node { left, right, .. } = self.nodes[node];
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+    }
+
+    fn push_down(&mut self, node: usize) {
+        let node = &mut self.nodes[node];
+        if node.reversed {
+            node.reversed = false;
+            std::mem::swap(&mut node.left, &mut node.right);
+            let children = [node.left, node.right];
+            for child in children.into_iter().flatten() {
+                self.nodes[child].reversed ^= true;
+            }
+        }
+    }
+
+    // Splits off the bottom `count` crates.
+    fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        let Some(node) = node else {
+            return (None, None);
+        };
+        self.push_down(node);
+
+        let left = self.nodes[node].left;
+        if self.size(left) < count {
+            let right = self.nodes[node].right;
+            let (middle, right) = self.split(right, count - self.size(left) - 1);
+            self.nodes[node].right = middle;
+            self.update(node);
+            (Some(node), right)
+        } else {
+            let (left, middle) = self.split(left, count);
+            self.nodes[node].left = middle;
+            self.update(node);
+            (left, Some(node))
+        }
+    }
+
+    fn merge(&mut self, bottom: Option<usize>, top: Option<usize>) -> Option<usize> {
+        let (bottom, top) = match (bottom, top) {
+            (Some(bottom), Some(top)) => (bottom, top),
+            (bottom, top) => return bottom.or(top),
+        };
+
+        if self.nodes[bottom].priority > self.nodes[top].priority {
+            self.push_down(bottom);
+            let right = self.nodes[bottom].right;
+            self.nodes[bottom].right = self.merge(right, Some(top));
+            self.update(bottom);
+            Some(bottom)
+        } else {
+            self.push_down(top);
+            let left = self.nodes[top].left;
+            self.nodes[top].left = self.merge(Some(bottom), left);
+            self.update(top);
+            Some(top)
+        }
+    }
+}
+
+impl From<Stacks> for RopeStacks {
+    fn from(stacks: Stacks) -> Self {
+        let mut rope = RopeStacks {
+            crates: Vec::new(),
+            nodes: Vec::new(),
+            stacks: Vec::new(),
+        };
+
+        for stack in stacks {
+            let mut root = None;
+            for label in stack {
+                let node = rope.nodes.len();
+                rope.crates.push(label);
+                rope.nodes.push(Node {
+                    priority: splitmix(node as u64),
+                    size: 1,
+                    reversed: false,
+                    left: None,
+                    right: None,
+                });
+                root = rope.merge(root, Some(node));
+            }
+            rope.stacks.push(root);
+        }
+
+        rope
+    }
+}
+
+fn splitmix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cranes::{CrateMover9000, CrateMover9001, MoveCrates},
+        drawing::parse_start_stacks,
+        parse_operations,
+        synthetic::generate,
+    };
+
+    #[test]
+    fn matches_the_vec_backend() {
+        for seed in 1..20 {
+            let input = generate(5, 6, 200, 8, seed);
+            let start = parse_start_stacks(&input).unwrap();
+            let operations = parse_operations(&input)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+
+            for preserve_order in [false, true] {
+                let mut rope = RopeStacks::from(start.clone());
+                let mut stacks = start.clone();
+                for &operation in &operations {
+                    rope.move_crates(operation, preserve_order).unwrap();
+                    stacks = if preserve_order {
+                        CrateMover9001.move_crates(stacks, operation)
+                    } else {
+                        CrateMover9000.move_crates(stacks, operation)
+                    }
+                    .unwrap();
+                    assert_eq!(rope.to_stacks(), stacks, "seed {seed}");
+                }
+
+                assert_eq!(rope.top_crates(), crate::top_crates(stacks));
+            }
+        }
+    }
+
+    #[test]
+    fn bad_moves_leave_the_stacks_alone() {
+        let start = vec![vec!["A".to_owned(), "B".to_owned()], vec![]];
+        let mut rope = RopeStacks::from(start.clone());
+        let operation = Operation {
+            line: 3,
+            count: 3,
+            from: 0,
+            to: 1,
+        };
+
+        assert!(rope.move_crates(operation, true).is_err());
+        assert!(rope
+            .move_crates(Operation { to: 2, ..operation }, true)
+            .is_err());
+        assert_eq!(rope.to_stacks(), start);
+    }
+}
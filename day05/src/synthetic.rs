@@ -0,0 +1,106 @@
+use crate::{
+    cranes::parse_crane,
+    drawing::{parse_start_stacks, render},
+    parse_operations,
+    rope::RopeStacks,
+    Error, Operation,
+};
+use std::{fmt::Write, time::Instant};
+
+// xorshift64*, enough to make reproducible procedures without dependencies.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as usize % bound
+    }
+}
+
+pub(crate) fn generate(
+    stacks: usize,
+    height: usize,
+    operations: usize,
+    max_count: usize,
+    seed: u64,
+) -> String {
+    assert!(stacks > 1 && height > 0 && max_count > 0);
+
+    let mut random = Random(seed.max(1));
+    let mut heights = vec![height; stacks];
+
+    let start = (0..stacks)
+        .map(|stack| {
+            (0..height)
+                .map(|level| char::from(b'A' + ((stack + level) % 26) as u8).to_string())
+                .collect()
+        })
+        .collect::<Vec<_>>();
+
+    let mut procedure = render(&start);
+    procedure.push('\n');
+
+    for _ in 0..operations {
+        let from = loop {
+            let from = random.below(stacks);
+            if heights[from] > 0 {
+                break from;
+            }
+        };
+        let to = random.below(stacks);
+        let count = 1 + random.below(heights[from].min(max_count));
+
+        heights[from] -= count;
+        heights[to] += count;
+
+        let operation = Operation {
+            line: 0,
+            count,
+            from,
+            to,
+        };
+        writeln!(procedure, "{operation}").expect("writing to a String cannot fail");
+    }
+
+    procedure
+}
+
+pub(crate) fn benchmark(input: &str) -> Result<(), Error> {
+    let start = parse_start_stacks(input)?;
+    let operations = parse_operations(input).collect::<Result<Vec<_>, _>>()?;
+    let lifted = operations
+        .iter()
+        .map(|operation| operation.count)
+        .sum::<usize>();
+    println!(
+        "{} stacks, {} crates, {} operations lifting {lifted} crates",
+        start.len(),
+        start.iter().map(Vec::len).sum::<usize>(),
+        operations.len()
+    );
+
+    for (model, preserve_order) in [("9000", false), ("9001", true)] {
+        let mut crane = parse_crane(model).expect("built-in crane model");
+        let timer = Instant::now();
+        let stacks = operations
+            .iter()
+            .try_fold(start.clone(), |stacks, &operation| {
+                crane.move_crates(stacks, operation)
+            })?;
+        let vec = timer.elapsed();
+
+        let timer = Instant::now();
+        let mut rope = RopeStacks::from(start.clone());
+        for &operation in &operations {
+            rope.move_crates(operation, preserve_order)?;
+        }
+        let treap = timer.elapsed();
+
+        assert_eq!(rope.to_stacks(), stacks);
+        println!("CrateMover {model}: Vec {vec:?}, rope {treap:?}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::{Operation, Stacks};
+use std::io::{self, Write};
+
+// `stacks` is the state after the operation; the heights before it and the
+// crates it moved can be read back off that state.
+pub(crate) fn write_record<W: Write>(
+    out: &mut W,
+    operation: &Operation,
+    stacks: &Stacks,
+) -> io::Result<()> {
+    let after = stacks.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut before = after.clone();
+    before[operation.to] -= operation.count;
+    before[operation.from] += operation.count;
+
+    let destination = &stacks[operation.to];
+    let moved = &destination[destination.len() - operation.count..];
+
+    writeln!(
+        out,
+        r#"{{"line":{},"operation":"{operation}","from":{},"to":{},"crates":[{}],"before":[{}],"after":[{}]}}"#,
+        operation.line,
+        operation.from + 1,
+        operation.to + 1,
+        join(moved.iter().map(|label| json_string(label))),
+        join(before),
+        join(after),
+    )
+}
+
+fn join<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn json_string(text: &str) -> String {
+    let mut string = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => string.push_str(r#"\""#),
+            '\\' => string.push_str(r"\\"),
+            c if c.is_control() => string.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cranes::{CrateMover9000, CrateMover9001},
+        get_top_crates_with_trace,
+    };
+
+    const EXAMPLE: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\nmove 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2\n";
+
+    #[test]
+    fn traces_each_operation() {
+        let mut out = Vec::new();
+        let top_crates = get_top_crates_with_trace(EXAMPLE, &mut CrateMover9000, &mut out);
+        assert_eq!(top_crates.unwrap(), Ok("CMZ".to_owned()));
+
+        let trace = String::from_utf8(out).unwrap();
+        let lines = trace.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            r#"{"line":6,"operation":"move 1 from 2 to 1","from":2,"to":1,"crates":["D"],"before":[2,3,1],"after":[3,2,1]}"#
+        );
+        assert_eq!(
+            lines[1],
+            r#"{"line":7,"operation":"move 3 from 1 to 3","from":1,"to":3,"crates":["D","N","Z"],"before":[3,2,1],"after":[0,2,4]}"#
+        );
+
+        let mut out = Vec::new();
+        let top_crates = get_top_crates_with_trace(EXAMPLE, &mut CrateMover9001, &mut out);
+        assert_eq!(top_crates.unwrap(), Ok("MCD".to_owned()));
+        let trace = String::from_utf8(out).unwrap();
+        assert!(trace
+            .lines()
+            .nth(1)
+            .unwrap()
+            .contains(r#""crates":["Z","N","D"]"#));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(json_string(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+}
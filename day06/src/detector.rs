@@ -0,0 +1,175 @@
+use std::io::{self, Read};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct Detection {
+    pub(crate) size: usize,
+    pub(crate) offset: usize,
+}
+
+// After a window reports a marker it starts over, so every marker in a
+// continuous stream is made of bytes no earlier marker of that size used.
+#[derive(Debug, Clone)]
+pub(crate) struct MarkerDetector {
+    windows: Vec<Window>,
+    history: Vec<u8>,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Window {
+    size: usize,
+    occurrences: [usize; 256],
+    repeated: usize,
+    filled: usize,
+    detected: bool,
+}
+
+impl MarkerDetector {
+    pub(crate) fn new(sizes: &[usize]) -> Self {
+        assert!(sizes.iter().all(|&size| size > 0));
+
+        MarkerDetector {
+            windows: sizes
+                .iter()
+                .map(|&size| Window {
+                    size,
+                    occurrences: [0; 256],
+                    repeated: 0,
+                    filled: 0,
+                    detected: false,
+                })
+                .collect(),
+            history: vec![0; sizes.iter().copied().max().unwrap_or(1)],
+            offset: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, byte: u8) -> impl Iterator<Item = Detection> + '_ {
+        let capacity = self.history.len();
+
+        for window in &mut self.windows {
+            window.detected = false;
+
+            window.occurrences[byte as usize] += 1;
+            if window.occurrences[byte as usize] == 2 {
+                window.repeated += 1;
+            }
+
+            if window.filled == window.size {
+                let leaving = self.history[(self.offset - window.size) % capacity] as usize;
+                window.occurrences[leaving] -= 1;
+                if window.occurrences[leaving] == 1 {
+                    window.repeated -= 1;
+                }
+            } else {
+                window.filled += 1;
+            }
+
+            if window.filled == window.size && window.repeated == 0 {
+                window.detected = true;
+                window.occurrences = [0; 256];
+                window.filled = 0;
+            }
+        }
+
+        self.history[self.offset % capacity] = byte;
+        self.offset += 1;
+
+        let offset = self.offset;
+        self.windows
+            .iter()
+            .filter(|window| window.detected)
+            .map(move |window| Detection {
+                size: window.size,
+                offset,
+            })
+    }
+
+    pub(crate) fn read<R: Read>(
+        &mut self,
+        mut reader: R,
+        mut report: impl FnMut(Detection),
+    ) -> io::Result<()> {
+        let mut buffer = [0; 8192];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(read) => read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            for &byte in &buffer[..read] {
+                self.push(byte).for_each(&mut report);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::marker_end;
+
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((&byte, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = byte;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    fn detections(input: &str, sizes: &[usize]) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        MarkerDetector::new(sizes)
+            .read(Trickle(input.as_bytes()), |detection| {
+                detections.push(detection)
+            })
+            .unwrap();
+        detections
+    }
+
+    #[test]
+    fn first_markers_match_the_search() {
+        for input in [
+            "mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+            "bvwbjplbgvbhsrlpgdmjqwftvncz",
+            "nppdvjthqldpwncqszvftbrmjlhg",
+            "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+            "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
+        ] {
+            let detections = detections(input, &[4, 14]);
+            for size in [4, 14] {
+                assert_eq!(
+                    detections
+                        .iter()
+                        .find(|detection| detection.size == size)
+                        .map(|detection| detection.offset),
+                    marker_end(input.as_bytes(), size),
+                    "{input}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_marker_is_reported_in_stream_order() {
+        assert_eq!(
+            detections("abcdaabcdefg", &[4, 3]),
+            [
+                Detection { size: 3, offset: 3 },
+                Detection { size: 4, offset: 4 },
+                Detection { size: 3, offset: 8 },
+                Detection { size: 4, offset: 9 },
+                Detection {
+                    size: 3,
+                    offset: 11
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::marker_end;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum FrameKind {
+    Packet,
+    Message,
+}
+
+// `marker` is where the marker starts and `offset` where its payload starts.
+// A frame is incomplete when the stream ends before the next marker, so a
+// marker that ends the stream has an empty, incomplete frame.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Frame<'a> {
+    pub(crate) kind: FrameKind,
+    pub(crate) marker: usize,
+    pub(crate) offset: usize,
+    pub(crate) payload: &'a [u8],
+    pub(crate) complete: bool,
+}
+
+// Packets and messages alternate, starting with the first packet marker; any
+// bytes before it are not part of a frame.
+#[derive(Debug, Clone)]
+pub(crate) struct Frames<'a> {
+    input: &'a [u8],
+    packet_size: usize,
+    message_size: usize,
+    next: Option<(FrameKind, usize)>,
+}
+
+impl<'a> Frames<'a> {
+    pub(crate) fn new(input: &'a [u8], packet_size: usize, message_size: usize) -> Self {
+        assert!(packet_size > 0 && message_size > 0);
+
+        Frames {
+            input,
+            packet_size,
+            message_size,
+            next: marker_end(input, packet_size).map(|end| (FrameKind::Packet, end)),
+        }
+    }
+
+    fn size(&self, kind: FrameKind) -> usize {
+        match kind {
+            FrameKind::Packet => self.packet_size,
+            FrameKind::Message => self.message_size,
+        }
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (kind, offset) = self.next.take()?;
+        let following = match kind {
+            FrameKind::Packet => FrameKind::Message,
+            FrameKind::Message => FrameKind::Packet,
+        };
+        let size = self.size(following);
+
+        let end = match marker_end(&self.input[offset..], size) {
+            Some(end) => {
+                self.next = Some((following, offset + end));
+                offset + end - size
+            }
+            None => self.input.len(),
+        };
+
+        Some(Frame {
+            kind,
+            marker: offset - self.size(kind),
+            offset,
+            payload: &self.input[offset..end],
+            complete: self.next.is_some(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::start_of_message;
+
+    #[test]
+    fn frames_alternate_between_packets_and_messages() {
+        let frames = Frames::new(b"zzabcdqqqfghijkrrwxyzss", 4, 6).collect::<Vec<_>>();
+
+        assert_eq!(
+            frames,
+            [
+                Frame {
+                    kind: FrameKind::Packet,
+                    marker: 1,
+                    offset: 5,
+                    payload: b"dqq",
+                    complete: true,
+                },
+                Frame {
+                    kind: FrameKind::Message,
+                    marker: 8,
+                    offset: 14,
+                    payload: b"kr",
+                    complete: true,
+                },
+                Frame {
+                    kind: FrameKind::Packet,
+                    marker: 16,
+                    offset: 20,
+                    payload: b"zss",
+                    complete: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn streams_without_markers_have_no_frames() {
+        assert_eq!(Frames::new(b"aabbaabb", 3, 5).count(), 0);
+        assert_eq!(
+            Frames::new(b"xabc", 3, 5).collect::<Vec<_>>(),
+            [Frame {
+                kind: FrameKind::Packet,
+                marker: 0,
+                offset: 3,
+                payload: b"c",
+                complete: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn markers_that_end_the_stream_have_empty_frames() {
+        assert_eq!(
+            Frames::new(b"aabcd", 4, 14).collect::<Vec<_>>(),
+            [Frame {
+                kind: FrameKind::Packet,
+                marker: 1,
+                offset: 5,
+                payload: b"",
+                complete: false,
+            }]
+        );
+        assert_eq!(
+            Frames::new(b"abcxyzw", 3, 4).collect::<Vec<_>>(),
+            [
+                Frame {
+                    kind: FrameKind::Packet,
+                    marker: 0,
+                    offset: 3,
+                    payload: b"",
+                    complete: true,
+                },
+                Frame {
+                    kind: FrameKind::Message,
+                    marker: 3,
+                    offset: 7,
+                    payload: b"",
+                    complete: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn first_frames_agree_with_the_marker_search() {
+        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        let mut frames = Frames::new(input.as_bytes(), 4, 14);
+
+        let packet = frames.next().unwrap();
+        assert_eq!(Some(packet.offset), start_of_message(input, 4));
+        let message = frames.next().unwrap();
+        assert_eq!(
+            Some(message.offset),
+            start_of_message(&input[packet.offset..], 14).map(|end| end + packet.offset)
+        );
+    }
+}
@@ -1,15 +1,290 @@
+use detector::MarkerDetector;
+use framing::{FrameKind, Frames};
+#[cfg(test)]
 use std::collections::HashSet;
+use std::{collections::HashMap, env, io, process};
 
+mod detector;
+mod framing;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Marker {
+    chars: usize,
+    bytes: usize,
+}
+
+#[cfg(test)]
 fn count_unique(string: &str) -> usize {
     string.chars().collect::<HashSet<_>>().len()
 }
 
-fn start_of_message(input: &str, n: usize) -> Option<usize> {
+#[cfg(test)]
+fn start_of_message_naive(input: &str, n: usize) -> Option<usize> {
     (n..input.len()).find(|&pos| count_unique(&input[pos - n..pos]) == n)
 }
 
+// Like the original search, a marker is only reported when at least one
+// character follows it.
+fn start_of_message<S: AsRef<[u8]> + ?Sized>(input: &S, n: usize) -> Option<usize> {
+    let bytes = input.as_ref();
+    if n == 0 {
+        return (!bytes.is_empty()).then_some(0);
+    }
+
+    marker_end(bytes, n).filter(|&end| end < bytes.len())
+}
+
+// Finds the end of the first marker, including one that ends the input.
+fn marker_end(bytes: &[u8], n: usize) -> Option<usize> {
+    assert!(n > 0);
+
+    let mut occurrences = [0usize; 256];
+    let mut repeated = 0;
+
+    for (pos, &byte) in bytes.iter().enumerate() {
+        occurrences[byte as usize] += 1;
+        if occurrences[byte as usize] == 2 {
+            repeated += 1;
+        }
+
+        if pos >= n {
+            let leaving = bytes[pos - n] as usize;
+            occurrences[leaving] -= 1;
+            if occurrences[leaving] == 1 {
+                repeated -= 1;
+            }
+        }
+
+        if pos + 1 >= n && repeated == 0 {
+            return Some(pos + 1);
+        }
+    }
+
+    None
+}
+
+fn find_marker(input: &str, n: usize) -> Option<Marker> {
+    if input.is_ascii() {
+        return start_of_message(input, n).map(|pos| Marker {
+            chars: pos,
+            bytes: pos,
+        });
+    }
+
+    if n == 0 {
+        return (!input.is_empty()).then_some(Marker { chars: 0, bytes: 0 });
+    }
+
+    let mut occurrences = HashMap::new();
+    let mut repeated = 0;
+    let mut leaving = input.chars();
+
+    for (pos, (offset, c)) in input.char_indices().enumerate() {
+        let count = occurrences.entry(c).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            repeated += 1;
+        }
+
+        if pos >= n {
+            let count = occurrences.get_mut(&leaving.next()?)?;
+            *count -= 1;
+            if *count == 1 {
+                repeated -= 1;
+            }
+        }
+
+        let end = offset + c.len_utf8();
+        if pos + 1 >= n && end < input.len() && repeated == 0 {
+            return Some(Marker {
+                chars: pos + 1,
+                bytes: end,
+            });
+        }
+    }
+
+    None
+}
+
 fn main() {
+    if env::args().any(|arg| arg == "--stream") {
+        let sizes = env::args()
+            .skip_while(|arg| arg != "--sizes")
+            .nth(1)
+            .map(|sizes| {
+                sizes
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .unwrap_or(Ok(vec![4, 14]));
+        let sizes = match sizes {
+            Ok(sizes) if !sizes.is_empty() && !sizes.contains(&0) => sizes,
+            _ => {
+                eprintln!("expected --sizes to be a comma-separated list of window sizes");
+                process::exit(1);
+            }
+        };
+
+        let mut detector = MarkerDetector::new(&sizes);
+        let result = detector.read(io::stdin().lock(), |detection| {
+            println!(
+                "{}-byte marker ends at offset {}",
+                detection.size, detection.offset
+            )
+        });
+        if let Err(error) = result {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+        return;
+    }
+
     const INPUT: &str = include_str!("input.txt");
-    println!("Part 1: {:?}", start_of_message(INPUT, 4));
-    println!("Part 2: {:?}", start_of_message(INPUT, 14));
+
+    if env::args().any(|arg| arg == "--frames") {
+        let size = |name, default| {
+            env::args()
+                .skip_while(|arg| arg != name)
+                .nth(1)
+                .and_then(|size| size.parse().ok())
+                .filter(|&size| size > 0)
+                .unwrap_or(default)
+        };
+
+        for frame in Frames::new(INPUT.as_bytes(), size("--packet", 4), size("--message", 14)) {
+            let kind = match frame.kind {
+                FrameKind::Packet => "packet",
+                FrameKind::Message => "message",
+            };
+            println!(
+                "{kind} marker at {}, payload at {} ({} bytes){}",
+                frame.marker,
+                frame.offset,
+                frame.payload.len(),
+                if frame.complete { "" } else { ", truncated" }
+            );
+        }
+        return;
+    }
+
+    for (part, n) in [(1, 4), (2, 14)] {
+        match find_marker(INPUT, n) {
+            Some(marker) if marker.chars == marker.bytes => {
+                println!("Part {part}: {}", marker.chars)
+            }
+            Some(marker) => println!(
+                "Part {part}: {} (byte offset {})",
+                marker.chars, marker.bytes
+            ),
+            None => println!("Part {part}: no marker"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let examples = [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+
+        for (input, packet, message) in examples {
+            assert_eq!(start_of_message(input, 4), Some(packet));
+            assert_eq!(start_of_message(input, 14), Some(message));
+        }
+    }
+
+    #[test]
+    fn markers_at_the_edges() {
+        assert_eq!(start_of_message("aabcd", 4), None);
+        assert_eq!(start_of_message("aabcde", 4), Some(5));
+        assert_eq!(start_of_message("abc", 4), None);
+        assert_eq!(start_of_message("", 1), None);
+        assert_eq!(start_of_message("", 0), None);
+        assert_eq!(start_of_message("a", 0), Some(0));
+        assert_eq!(start_of_message("aaaa", 1), Some(1));
+    }
+
+    #[test]
+    fn unicode_markers_report_chars_and_bytes() {
+        assert_eq!(
+            find_marker("ééaé→b😀", 3),
+            Some(Marker {
+                chars: 5,
+                bytes: 10
+            })
+        );
+        assert_eq!(find_marker("ééaé→b😀", 5), None);
+        assert_eq!(
+            find_marker("ééaé→b😀!", 5),
+            Some(Marker {
+                chars: 7,
+                bytes: 15
+            })
+        );
+        assert_eq!(find_marker("ééé", 2), None);
+        assert_eq!(find_marker("abcde", 4), Some(Marker { chars: 4, bytes: 4 }));
+    }
+
+    #[test]
+    fn unicode_search_matches_a_char_by_char_search() {
+        let alphabet = ['a', 'é', 'ß', '→', '😀', 'Ω'];
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut random = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % bound
+        };
+
+        for _ in 0..2000 {
+            let chars = (0..random(30))
+                .map(|_| alphabet[random(alphabet.len())])
+                .collect::<Vec<_>>();
+            let input = chars.iter().collect::<String>();
+
+            for n in 1..=alphabet.len() + 1 {
+                let expected = (n..chars.len())
+                    .find(|&pos| chars[pos - n..pos].iter().collect::<HashSet<_>>().len() == n)
+                    .map(|pos| Marker {
+                        chars: pos,
+                        bytes: chars[..pos].iter().map(|c| c.len_utf8()).sum(),
+                    });
+                assert_eq!(find_marker(&input, n), expected, "{input:?}, {n}");
+            }
+        }
+    }
+
+    #[test]
+    fn matches_the_naive_search() {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..2000 {
+            let alphabet = 1 + random(26) as u8;
+            let input = (0..random(60))
+                .map(|_| char::from(b'a' + random(alphabet as u64) as u8))
+                .collect::<String>();
+            for n in 0..16 {
+                assert_eq!(
+                    start_of_message(&input, n),
+                    start_of_message_naive(&input, n),
+                    "{input:?}, {n}"
+                );
+            }
+        }
+    }
 }
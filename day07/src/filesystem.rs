@@ -0,0 +1,190 @@
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct NodeId(usize);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum NodeKind {
+    Directory { children: Vec<NodeId> },
+    File { size: usize },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl FileSystem {
+    pub(crate) fn new() -> Self {
+        FileSystem {
+            nodes: vec![Node {
+                name: "/".to_owned(),
+                parent: None,
+                kind: NodeKind::Directory {
+                    children: Vec::new(),
+                },
+            }],
+        }
+    }
+
+    pub(crate) fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub(crate) fn name(&self, node: NodeId) -> &str {
+        &self.nodes[node.0].name
+    }
+
+    pub(crate) fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node.0].parent
+    }
+
+    pub(crate) fn kind(&self, node: NodeId) -> &NodeKind {
+        &self.nodes[node.0].kind
+    }
+
+    pub(crate) fn is_dir(&self, node: NodeId) -> bool {
+        matches!(self.kind(node), NodeKind::Directory { .. })
+    }
+
+    pub(crate) fn children(&self, node: NodeId) -> &[NodeId] {
+        match self.kind(node) {
+            NodeKind::Directory { children } => children,
+            NodeKind::File { .. } => &[],
+        }
+    }
+
+    pub(crate) fn child(&self, node: NodeId, name: &str) -> Option<NodeId> {
+        self.children(node)
+            .iter()
+            .copied()
+            .find(|&child| self.name(child) == name)
+    }
+
+    pub(crate) fn size(&self, node: NodeId) -> usize {
+        match self.kind(node) {
+            NodeKind::Directory { children } => {
+                children.iter().map(|&child| self.size(child)).sum()
+            }
+            NodeKind::File { size } => *size,
+        }
+    }
+
+    pub(crate) fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self.root(), |node, name| self.child(node, name))
+    }
+
+    pub(crate) fn path(&self, node: NodeId) -> String {
+        let mut names = Vec::new();
+        let mut current = node;
+        while let Some(parent) = self.parent(current) {
+            names.push(self.name(current));
+            current = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    // Pre-order, with each directory's children in the order they were listed.
+    pub(crate) fn walk(&self) -> impl Iterator<Item = NodeId> + '_ {
+        let mut pending = vec![self.root()];
+        std::iter::from_fn(move || {
+            let node = pending.pop()?;
+            pending.extend(self.children(node).iter().rev());
+            Some(node)
+        })
+    }
+
+    pub(crate) fn find<'a>(&'a self, name: &'a str) -> impl Iterator<Item = NodeId> + 'a {
+        self.walk().filter(move |&node| self.name(node) == name)
+    }
+
+    // Both return `None` when the name is already taken by the other kind of
+    // node. Listing a file again replaces it rather than adding it twice.
+    pub(crate) fn directory(&mut self, parent: NodeId, name: &str) -> Option<NodeId> {
+        match self.child(parent, name) {
+            Some(node) => self.is_dir(node).then_some(node),
+            None => Some(self.insert(
+                parent,
+                name,
+                NodeKind::Directory {
+                    children: Vec::new(),
+                },
+            )),
+        }
+    }
+
+    pub(crate) fn file(&mut self, parent: NodeId, name: &str, size: usize) -> Option<NodeId> {
+        match self.child(parent, name) {
+            Some(node) if self.is_dir(node) => None,
+            Some(node) => {
+                self.nodes[node.0].kind = NodeKind::File { size };
+                Some(node)
+            }
+            None => Some(self.insert(parent, name, NodeKind::File { size })),
+        }
+    }
+
+    fn insert(&mut self, parent: NodeId, name: &str, kind: NodeKind) -> NodeId {
+        let node = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            parent: Some(parent),
+            kind,
+        });
+        if let NodeKind::Directory { children } = &mut self.nodes[parent.0].kind {
+            children.push(node);
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_and_queries_a_tree() {
+        let mut filesystem = FileSystem::new();
+        let root = filesystem.root();
+        let a = filesystem.directory(root, "a").unwrap();
+        let e = filesystem.directory(a, "e").unwrap();
+        filesystem.file(e, "i", 584);
+        filesystem.file(a, "f", 29116);
+        filesystem.file(root, "b.txt", 14848514);
+        assert_eq!(filesystem.directory(root, "a"), Some(a));
+        let f = filesystem.file(a, "f", 29116).unwrap();
+        assert_eq!(filesystem.children(a).len(), 2);
+        assert_eq!(filesystem.file(root, "a", 10), None);
+        assert_eq!(filesystem.directory(a, "f"), None);
+        assert_eq!(filesystem.children(a).len(), 2);
+        assert_eq!(filesystem.kind(f), &NodeKind::File { size: 29116 });
+
+        assert_eq!(filesystem.lookup("/a/e"), Some(e));
+        assert_eq!(filesystem.lookup("/"), Some(root));
+        assert_eq!(filesystem.lookup("/a/x"), None);
+        assert_eq!(filesystem.size(a), 29700);
+        assert_eq!(filesystem.size(root), 14878214);
+        assert_eq!(filesystem.path(e), "/a/e");
+        assert_eq!(filesystem.parent(e), Some(a));
+
+        let names = filesystem
+            .walk()
+            .map(|node| filesystem.name(node))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["/", "a", "e", "i", "f", "b.txt"]);
+
+        let found = filesystem.find("i").collect::<Vec<_>>();
+        assert_eq!(found.len(), 1);
+        assert_eq!(filesystem.path(found[0]), "/a/e/i");
+        assert!(!filesystem.is_dir(found[0]));
+        assert_eq!(filesystem.children(found[0]), []);
+    }
+}
@@ -1,71 +1,333 @@
-use std::collections::HashMap;
+use filesystem::{FileSystem, NodeId};
+use std::{env, fmt, process};
 
-fn directory_sizes(input: &str) -> HashMap<String, usize> {
-    #[derive(Debug)]
-    struct State<'input> {
-        cd: Vec<&'input str>,
-        sizes: HashMap<String, usize>,
+mod filesystem;
+mod render;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum TranscriptErrorKind {
+    UnknownCommand,
+    OutputWithoutLs,
+    MalformedListing,
+    BadSize(String),
+    UnlistedDirectory(String),
+    AboveRoot,
+    NameClash(String),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct TranscriptError {
+    line: usize,
+    text: String,
+    kind: TranscriptErrorKind,
+}
+
+fn parse_transcript(input: &str) -> Result<FileSystem, TranscriptError> {
+    let mut filesystem = FileSystem::new();
+    let mut cwd = filesystem.root();
+    let mut listing = false;
+
+    for (line, text) in (1..).zip(input.lines()) {
+        let error = |kind| TranscriptError {
+            line,
+            text: text.to_owned(),
+            kind,
+        };
+
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+        match tokens.as_slice() {
+            ["$", "cd", path] => {
+                cwd = change_directory(&filesystem, cwd, path).map_err(error)?;
+                listing = false;
+            }
+            ["$", "ls"] => listing = true,
+            ["$", ..] => return Err(error(TranscriptErrorKind::UnknownCommand)),
+            _ if !listing => return Err(error(TranscriptErrorKind::OutputWithoutLs)),
+            ["dir", dir] => {
+                filesystem
+                    .directory(cwd, dir)
+                    .ok_or_else(|| error(TranscriptErrorKind::NameClash(dir.to_string())))?;
+            }
+            [size, name] => {
+                let size = size
+                    .parse()
+                    .map_err(|_| error(TranscriptErrorKind::BadSize(size.to_string())))?;
+                filesystem
+                    .file(cwd, name, size)
+                    .ok_or_else(|| error(TranscriptErrorKind::NameClash(name.to_string())))?;
+            }
+            _ => return Err(error(TranscriptErrorKind::MalformedListing)),
+        };
     }
 
-    input
-        .lines()
-        .fold(
-            State {
-                cd: vec!["~"],
-                sizes: HashMap::default(),
-            },
-            |mut state, line| {
-                let tokens = line.split_whitespace().collect::<Vec<_>>();
-                match tokens.as_slice() {
-                    ["$", "cd", "/"] => state.cd = vec!["~"],
-                    ["$", "cd", ".."] => {
-                        state.cd.pop();
-                    }
-                    ["$", "cd", dir] => state.cd.push(dir),
-                    ["$", "ls"] => {}
-                    ["dir", _] => {}
-                    [size, _] => {
-                        let size = size.parse().unwrap();
-
-                        for i in 0..state.cd.len() {
-                            state
-                                .sizes
-                                .entry(state.cd[..=i].join("/"))
-                                .and_modify(|total| *total += size)
-                                .or_insert(size);
-                        }
-                    }
-                    _ => panic!("unexpected"),
-                };
-                state
-            },
-        )
-        .sizes
+    Ok(filesystem)
 }
 
-fn part_1(input: &str) -> usize {
-    directory_sizes(input)
-        .values()
-        .filter(|&size| *size <= 100_000)
-        .sum()
+fn change_directory(
+    filesystem: &FileSystem,
+    cwd: NodeId,
+    path: &str,
+) -> Result<NodeId, TranscriptErrorKind> {
+    let start = if path.starts_with('/') {
+        filesystem.root()
+    } else {
+        cwd
+    };
+
+    path.split('/').try_fold(start, |dir, name| match name {
+        "" | "." => Ok(dir),
+        ".." => filesystem.parent(dir).ok_or(TranscriptErrorKind::AboveRoot),
+        name => filesystem
+            .child(dir, name)
+            .filter(|&child| filesystem.is_dir(child))
+            .ok_or_else(|| TranscriptErrorKind::UnlistedDirectory(name.to_owned())),
+    })
+}
+
+fn directories(filesystem: &FileSystem) -> impl Iterator<Item = NodeId> + '_ {
+    filesystem.walk().filter(|&node| filesystem.is_dir(node))
 }
 
-fn part_2(input: &str) -> usize {
-    let sizes = directory_sizes(input);
+fn part_1(filesystem: &FileSystem) -> usize {
+    directories(filesystem)
+        .map(|dir| filesystem.size(dir))
+        .filter(|&size| size <= 100_000)
+        .sum()
+}
 
+fn part_2(filesystem: &FileSystem) -> Option<usize> {
     const TOTAL: usize = 70_000_000;
     const REQUIRED: usize = 30_000_000;
-    let need_to_delete = REQUIRED - (TOTAL - sizes["~"]);
+    let unused = TOTAL.saturating_sub(filesystem.size(filesystem.root()));
+    let need_to_delete = REQUIRED.saturating_sub(unused);
 
-    sizes
-        .values()
-        .filter(|&size| *size >= need_to_delete)
+    directories(filesystem)
+        .map(|dir| filesystem.size(dir))
+        .filter(|&size| size >= need_to_delete)
         .min()
-        .copied()
-        .unwrap()
+}
+
+impl fmt::Display for TranscriptErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscriptErrorKind::UnknownCommand => write!(f, "unknown command"),
+            TranscriptErrorKind::OutputWithoutLs => write!(f, "output without a preceding `$ ls`"),
+            TranscriptErrorKind::MalformedListing => {
+                write!(f, "expected `dir NAME` or `SIZE NAME` in `ls` output")
+            }
+            TranscriptErrorKind::BadSize(size) => write!(f, "bad file size {size:?}"),
+            TranscriptErrorKind::UnlistedDirectory(name) => {
+                write!(f, "no directory {name:?} has been listed here")
+            }
+            TranscriptErrorKind::AboveRoot => write!(f, "cannot move above the root directory"),
+            TranscriptErrorKind::NameClash(name) => {
+                write!(f, "{name:?} is listed as both a file and a directory")
+            }
+        }
+    }
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} in {:?}", self.line, self.kind, self.text)
+    }
 }
 
 fn main() {
-    println!("Part 1: {}", part_1(include_str!("input.txt")));
-    println!("Part 2: {}", part_2(include_str!("input.txt")));
+    let option = |name| env::args().skip_while(|arg| arg != name).nth(1);
+
+    let filesystem = match parse_transcript(include_str!("input.txt")) {
+        Ok(filesystem) => filesystem,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+    };
+
+    if env::args().any(|arg| arg == "--tree") {
+        print!("{}", render::tree(&filesystem));
+        return;
+    }
+
+    if env::args().any(|arg| arg == "--du") {
+        let depth = option("--depth").and_then(|depth| depth.parse().ok());
+        let human_readable = env::args().any(|arg| arg == "-h" || arg == "--human-readable");
+        print!("{}", render::du(&filesystem, depth, human_readable));
+        return;
+    }
+
+    if let Some(path) = option("--size") {
+        match filesystem.lookup(&path) {
+            Some(node) => println!("{}", filesystem.size(node)),
+            None => {
+                eprintln!("{path}: no such file or directory");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(name) = option("--find") {
+        for node in filesystem.find(&name) {
+            println!("{} {}", filesystem.size(node), filesystem.path(node));
+        }
+        return;
+    }
+
+    println!("Part 1: {}", part_1(&filesystem));
+    match part_2(&filesystem) {
+        Some(size) => println!("Part 2: {size}"),
+        None => println!("Part 2: no directory frees enough space"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    pub(crate) const EXAMPLE: &str = "\
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+";
+
+    #[test]
+    fn example() {
+        let filesystem = parse_transcript(EXAMPLE).unwrap();
+        assert_eq!(part_1(&filesystem), 95437);
+        assert_eq!(part_2(&filesystem), Some(24933642));
+    }
+
+    #[test]
+    fn small_filesystems_need_nothing_deleted() {
+        let filesystem =
+            parse_transcript("$ cd /\n$ ls\n100 a\ndir b\n$ cd b\n$ ls\n50 c\n").unwrap();
+        assert_eq!(part_2(&filesystem), Some(50));
+    }
+
+    #[test]
+    fn transcripts_build_a_tree() {
+        let filesystem = parse_transcript(EXAMPLE).unwrap();
+        let size = |path| filesystem.lookup(path).map(|node| filesystem.size(node));
+
+        assert_eq!(size("/a/e"), Some(584));
+        assert_eq!(size("/a"), Some(94853));
+        assert_eq!(size("/d"), Some(24933642));
+        assert_eq!(size("/"), Some(48381165));
+        assert_eq!(size("/a/e/i"), Some(584));
+        assert_eq!(size("/x"), None);
+    }
+
+    #[test]
+    fn relisting_does_not_double_count() {
+        let input = format!(
+            "{EXAMPLE}$ cd /a\n$ ls\ndir e\n29116 f\n2557 g\n62596 h.lst\n$ cd e\n$ ls\n584 i\n"
+        );
+        assert_eq!(parse_transcript(&input), parse_transcript(EXAMPLE));
+    }
+
+    #[test]
+    fn paths_can_span_several_directories() {
+        let input = "$ cd /\n$ ls\ndir a\ndir c\n$ cd a\n$ ls\ndir b\n$ cd b/../../c\n$ ls\n10 x\n$ cd /a/b\n$ ls\n20 y\n$ cd ./..\n$ ls\n30 z\n";
+        let filesystem = parse_transcript(input).unwrap();
+        let size = |path| filesystem.lookup(path).map(|node| filesystem.size(node));
+
+        assert_eq!(size("/c/x"), Some(10));
+        assert_eq!(size("/a/b/y"), Some(20));
+        assert_eq!(size("/a/z"), Some(30));
+        assert_eq!(size("/"), Some(60));
+    }
+
+    #[test]
+    fn bad_transcripts_report_their_line() {
+        let error = |input: &str| {
+            let error = parse_transcript(input).unwrap_err();
+            (error.line, error.text, error.kind)
+        };
+
+        assert_eq!(
+            error("$ cd /\n$ rm -rf a\n"),
+            (
+                2,
+                "$ rm -rf a".to_owned(),
+                TranscriptErrorKind::UnknownCommand
+            )
+        );
+        assert_eq!(
+            error("$ cd /\n10 a\n"),
+            (2, "10 a".to_owned(), TranscriptErrorKind::OutputWithoutLs)
+        );
+        assert_eq!(
+            error("$ cd /\n$ ls\n10 a b\n"),
+            (
+                3,
+                "10 a b".to_owned(),
+                TranscriptErrorKind::MalformedListing
+            )
+        );
+        assert_eq!(
+            error("$ cd /\n$ ls\nten a\n"),
+            (
+                3,
+                "ten a".to_owned(),
+                TranscriptErrorKind::BadSize("ten".to_owned())
+            )
+        );
+        assert_eq!(
+            error("$ cd /\n$ ls\n10 a\n$ cd a\n"),
+            (
+                4,
+                "$ cd a".to_owned(),
+                TranscriptErrorKind::UnlistedDirectory("a".to_owned())
+            )
+        );
+        assert_eq!(
+            error("$ cd /\n$ ls\ndir x\n10 x\n"),
+            (
+                4,
+                "10 x".to_owned(),
+                TranscriptErrorKind::NameClash("x".to_owned())
+            )
+        );
+        assert_eq!(
+            error("$ cd /\n$ ls\n10 x\n$ ls\ndir x\n"),
+            (
+                5,
+                "dir x".to_owned(),
+                TranscriptErrorKind::NameClash("x".to_owned())
+            )
+        );
+    }
+
+    #[test]
+    fn moving_above_the_root_is_an_error() {
+        assert_eq!(
+            parse_transcript("$ cd /\n$ ls\ndir a\n$ cd a/../..\n"),
+            Err(TranscriptError {
+                line: 4,
+                text: "$ cd a/../..".to_owned(),
+                kind: TranscriptErrorKind::AboveRoot,
+            })
+        );
+    }
 }
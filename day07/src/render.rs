@@ -0,0 +1,140 @@
+use crate::filesystem::{FileSystem, NodeId, NodeKind};
+use std::fmt::Write;
+
+pub(crate) fn tree(filesystem: &FileSystem) -> String {
+    let mut tree = String::new();
+    write_tree(filesystem, filesystem.root(), 0, &mut tree);
+    tree
+}
+
+fn write_tree(filesystem: &FileSystem, node: NodeId, depth: usize, tree: &mut String) {
+    let indent = "  ".repeat(depth);
+    let name = filesystem.name(node);
+    match filesystem.kind(node) {
+        NodeKind::Directory { children } => {
+            writeln!(tree, "{indent}- {name} (dir)").expect("writing to a String cannot fail");
+
+            let mut children = children.clone();
+            children.sort_by_key(|&child| filesystem.name(child));
+            for child in children {
+                write_tree(filesystem, child, depth + 1, tree);
+            }
+        }
+        NodeKind::File { size } => {
+            writeln!(tree, "{indent}- {name} (file, size={size})")
+                .expect("writing to a String cannot fail");
+        }
+    }
+}
+
+// Like `du`: every directory, largest first, down to `max_depth` below the root.
+pub(crate) fn du(
+    filesystem: &FileSystem,
+    max_depth: Option<usize>,
+    human_readable: bool,
+) -> String {
+    let mut directories = Vec::new();
+    let mut pending = vec![(filesystem.root(), 0)];
+    while let Some((node, depth)) = pending.pop() {
+        if !filesystem.is_dir(node) || max_depth.is_some_and(|max_depth| depth > max_depth) {
+            continue;
+        }
+        directories.push((filesystem.size(node), filesystem.path(node)));
+        pending.extend(
+            filesystem
+                .children(node)
+                .iter()
+                .map(|&child| (child, depth + 1)),
+        );
+    }
+
+    directories.sort_by(|(a_size, a_path), (b_size, b_path)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+
+    directories
+        .into_iter()
+        .fold(String::new(), |mut listing, (size, path)| {
+            let size = if human_readable {
+                human(size)
+            } else {
+                size.to_string()
+            };
+            writeln!(listing, "{size}\t{path}").expect("writing to a String cannot fail");
+            listing
+        })
+}
+
+// Rounded up to one decimal below 10 and to a whole number above, as `du -h` does.
+fn human(size: usize) -> String {
+    let mut value = size as f64;
+    for unit in ["", "K", "M", "G", "T"] {
+        let rounded = if unit.is_empty() || value >= 10.0 {
+            value.ceil()
+        } else {
+            (value * 10.0).ceil() / 10.0
+        };
+
+        if rounded < 1024.0 || unit == "T" {
+            return if unit.is_empty() || rounded >= 10.0 {
+                format!("{rounded:.0}{unit}")
+            } else {
+                format!("{rounded:.1}{unit}")
+            };
+        }
+        value /= 1024.0;
+    }
+    unreachable!("the last unit always returns")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parse_transcript, test::EXAMPLE};
+
+    #[test]
+    fn renders_the_example_tree() {
+        let filesystem = parse_transcript(EXAMPLE).unwrap();
+        assert_eq!(
+            tree(&filesystem),
+            "\
+- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+"
+        );
+    }
+
+    #[test]
+    fn lists_directories_largest_first() {
+        let filesystem = parse_transcript(EXAMPLE).unwrap();
+        assert_eq!(
+            du(&filesystem, None, false),
+            "48381165\t/\n24933642\t/d\n94853\t/a\n584\t/a/e\n"
+        );
+        assert_eq!(du(&filesystem, Some(1), true), "47M\t/\n24M\t/d\n93K\t/a\n");
+        assert_eq!(du(&filesystem, Some(0), false), "48381165\t/\n");
+    }
+
+    #[test]
+    fn human_readable_sizes() {
+        assert_eq!(human(0), "0");
+        assert_eq!(human(1023), "1023");
+        assert_eq!(human(1024), "1.0K");
+        assert_eq!(human(1025), "1.1K");
+        assert_eq!(human(10 * 1024 - 1), "10K");
+        assert_eq!(human(1024 * 1024 - 1), "1.0M");
+        assert_eq!(human(584), "584");
+    }
+}